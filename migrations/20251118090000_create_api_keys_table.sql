@@ -0,0 +1,16 @@
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    tenant TEXT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT now() NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL,
+    CONSTRAINT unique_api_key_hash UNIQUE (key_hash)
+);
+
+CREATE INDEX idx_api_keys_tenant ON api_keys(tenant);
+CREATE INDEX idx_api_keys_created_at ON api_keys(created_at DESC);
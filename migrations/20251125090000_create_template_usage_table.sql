@@ -0,0 +1,15 @@
+CREATE TABLE template_usage (
+    template_code TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    render_count BIGINT NOT NULL DEFAULT 0,
+    last_rendered_at TIMESTAMP WITH TIME ZONE NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT now() NOT NULL,
+    PRIMARY KEY (template_code, version, language),
+    CONSTRAINT fk_template_usage_template
+        FOREIGN KEY (template_code, version, language)
+        REFERENCES templates (template_code, version, language)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX idx_template_usage_last_rendered_at ON template_usage(last_rendered_at);
\ No newline at end of file
@@ -1,13 +1,698 @@
-use redis::aio::ConnectionManager;
-use redis::{Client, RedisError};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{Config, RedisMode};
+use crate::middleware::metrics::CACHE_AVAILABLE;
+use crate::models::CacheKeyInfo;
+use crate::telemetry;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use redis::aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig, PubSub};
+use redis::cluster::{ClusterClient, ClusterClientBuilder};
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::{
+    Arg, AsyncCommands, Client, Cmd, ConnectionAddr, ConnectionInfo, ErrorKind, IntoConnectionInfo, Pipeline, RedisError,
+    RedisResult, Value,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::Instrument;
 
-pub type RedisPool = ConnectionManager;
+/// Pub/sub channel carrying JSON arrays of `template:` keys that replicas
+/// must drop from their in-process cache.
+pub const INVALIDATION_CHANNEL: &str = "templates:invalidations";
 
-pub async fn create_redis_pool(redis_url: &str) -> Result<RedisPool, RedisError> {
-    let client = Client::open(redis_url)?;
-    ConnectionManager::new(client).await
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Redis handle that tolerates Redis being down. Commands fail fast while
+/// there is no connection or the circuit breaker is open, so callers fall
+/// back to Postgres instead of waiting on timeouts.
+#[derive(Clone)]
+pub struct RedisPool {
+    inner: Arc<RedisState>,
+}
+
+struct RedisState {
+    topology: Topology,
+    conn: RwLock<Option<Connection>>,
+    breaker: CircuitBreaker,
+    /// Cluster node the next pub/sub connection is tried on first.
+    pubsub_node: AtomicUsize,
+}
+
+enum Topology {
+    Standalone {
+        client: Client,
+        manager_config: ConnectionManagerConfig,
+    },
+    Cluster {
+        client: ClusterClient,
+        nodes: Vec<ConnectionInfo>,
+        timeout: Duration,
+    },
+    /// The master is resolved through the sentinels on every (re)connect,
+    /// so a failover is picked up once the old master starts failing.
+    Sentinel {
+        sentinel: tokio::sync::Mutex<Sentinel>,
+        master: String,
+        node_info: SentinelNodeConnectionInfo,
+        manager_config: ConnectionManagerConfig,
+    },
+}
+
+#[derive(Clone)]
+enum Connection {
+    Single(Box<ConnectionManager>),
+    Cluster(ClusterConnection),
+}
+
+/// Builds the Redis handle for the configured topology without requiring
+/// Redis to be reachable. Only invalid configuration is an error; an
+/// unreachable server is retried in the background.
+pub async fn create_redis_pool(config: &Config) -> Result<RedisPool, RedisError> {
+    let timeout = Duration::from_millis(config.redis_timeout_ms);
+    let manager_config = ConnectionManagerConfig::new()
+        .set_connection_timeout(timeout)
+        .set_response_timeout(timeout)
+        .set_number_of_retries(1);
+
+    let topology = match config.redis_mode {
+        RedisMode::Standalone => Topology::Standalone {
+            client: Client::open(config.redis_url.as_str())?,
+            manager_config,
+        },
+        RedisMode::Cluster => {
+            let urls = if config.redis_cluster_nodes.is_empty() {
+                vec![config.redis_url.clone()]
+            } else {
+                config.redis_cluster_nodes.clone()
+            };
+            let nodes = urls
+                .iter()
+                .map(|url| url.as_str().into_connection_info())
+                .collect::<RedisResult<Vec<_>>>()?;
+            let client = ClusterClientBuilder::new(nodes.clone())
+                .connection_timeout(timeout)
+                .response_timeout(timeout)
+                .retries(1)
+                .build()?;
+            Topology::Cluster { client, nodes, timeout }
+        }
+        RedisMode::Sentinel => {
+            let master_info = config.redis_url.as_str().into_connection_info()?;
+            Topology::Sentinel {
+                sentinel: tokio::sync::Mutex::new(Sentinel::build(config.redis_sentinel_nodes.clone())?),
+                master: config.redis_sentinel_master.clone(),
+                node_info: SentinelNodeConnectionInfo {
+                    tls_mode: None,
+                    redis_connection_info: Some(master_info.redis),
+                },
+                manager_config,
+            }
+        }
+    };
+
+    let pool = RedisPool {
+        inner: Arc::new(RedisState {
+            topology,
+            conn: RwLock::new(None),
+            breaker: CircuitBreaker::new(
+                config.redis_breaker_threshold,
+                Duration::from_secs(config.redis_breaker_cooldown_secs),
+            ),
+            pubsub_node: AtomicUsize::new(0),
+        }),
+    };
+
+    if let Err(e) = pool.connect().await {
+        tracing::warn!("Redis unavailable, starting without cache: {}", e);
+        CACHE_AVAILABLE.set(0);
+    }
+    pool.spawn_reconnect();
+
+    Ok(pool)
+}
+
+impl RedisPool {
+    /// Whether commands are currently being sent to Redis.
+    pub fn is_available(&self) -> bool {
+        self.inner.conn.read().unwrap().is_some() && !self.inner.breaker.is_open()
+    }
+
+    async fn connect(&self) -> RedisResult<()> {
+        let conn = match &self.inner.topology {
+            Topology::Standalone { client, manager_config } => Connection::Single(Box::new(
+                ConnectionManager::new_with_config(client.clone(), manager_config.clone()).await?,
+            )),
+            Topology::Cluster { client, .. } => Connection::Cluster(client.get_async_connection().await?),
+            Topology::Sentinel { manager_config, .. } => Connection::Single(Box::new(
+                ConnectionManager::new_with_config(self.sentinel_master().await?, manager_config.clone()).await?,
+            )),
+        };
+
+        *self.inner.conn.write().unwrap() = Some(conn);
+        self.inner.breaker.record_success();
+        CACHE_AVAILABLE.set(1);
+        tracing::info!("Redis connection established");
+        Ok(())
+    }
+
+    async fn sentinel_master(&self) -> RedisResult<Client> {
+        match &self.inner.topology {
+            Topology::Sentinel { sentinel, master, node_info, .. } => {
+                sentinel.lock().await.async_master_for(master, Some(node_info)).await
+            }
+            _ => Err(RedisError::from((ErrorKind::ClientError, "Not in sentinel mode"))),
+        }
+    }
+
+    /// Reconnects while Redis is unreachable or the breaker is open. For
+    /// sentinel this re-resolves the master, which is how failovers are
+    /// followed; otherwise the connection managers reconnect on their own
+    /// and this only covers the initial connection.
+    fn spawn_reconnect(&self) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+                if pool.is_available() {
+                    continue;
+                }
+                if let Err(e) = pool.connect().await {
+                    tracing::debug!("Redis reconnect failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Pub/sub connection. Cluster and sentinel both propagate published
+    /// messages to every node, so any reachable node will do: cluster nodes
+    /// are tried in turn, starting after the one used by the previous
+    /// subscription so a reconnect moves away from a node that dropped it.
+    pub async fn pubsub(&self) -> RedisResult<PubSub> {
+        let client = match &self.inner.topology {
+            Topology::Standalone { client, .. } => client.clone(),
+            Topology::Cluster { nodes, timeout, .. } => return self.cluster_pubsub(nodes, *timeout).await,
+            Topology::Sentinel { .. } => self.sentinel_master().await?,
+        };
+        client.get_async_pubsub().await
+    }
+
+    async fn cluster_pubsub(&self, nodes: &[ConnectionInfo], timeout: Duration) -> RedisResult<PubSub> {
+        let start = self.inner.pubsub_node.load(Ordering::Relaxed);
+        let mut last_error = RedisError::from((ErrorKind::ClientError, "No cluster nodes configured"));
+        for offset in 0..nodes.len() {
+            let index = (start + offset) % nodes.len();
+            let connect = async { Client::open(nodes[index].clone())?.get_async_pubsub().await };
+            match tokio::time::timeout(timeout, connect).await {
+                Ok(Ok(pubsub)) => {
+                    self.inner.pubsub_node.store(index + 1, Ordering::Relaxed);
+                    return Ok(pubsub);
+                }
+                Ok(Err(e)) => last_error = e,
+                Err(_) => last_error = RedisError::from((ErrorKind::IoError, "Pub/sub connection timed out")),
+            }
+            tracing::debug!("Pub/sub node {} unavailable: {}", nodes[index].addr, last_error);
+        }
+        Err(last_error)
+    }
+
+    /// Direct clients for every cluster primary, for per-node commands such
+    /// as SCAN. `None` outside cluster mode.
+    pub async fn cluster_primaries(&self) -> RedisResult<Option<Vec<Client>>> {
+        let Topology::Cluster { nodes, .. } = &self.inner.topology else {
+            return Ok(None);
+        };
+
+        let mut conn = self.clone();
+        let slots: Vec<Value> = redis::cmd("CLUSTER").arg("SLOTS").query_async(&mut conn).await?;
+
+        let mut primaries: Vec<(String, u16)> = Vec::new();
+        for range in slots {
+            // [start, end, [host, port, id], replicas...]
+            let Value::Array(fields) = range else { continue };
+            let Some(Value::Array(primary)) = fields.get(2) else { continue };
+            let (Some(host), Some(port)) = (primary.first(), primary.get(1)) else { continue };
+            let host: String = redis::from_redis_value(host)?;
+            let port: u16 = redis::from_redis_value(port)?;
+            if !primaries.contains(&(host.clone(), port)) {
+                primaries.push((host, port));
+            }
+        }
+
+        primaries
+            .into_iter()
+            .map(|(host, port)| {
+                let mut info = nodes[0].clone();
+                info.addr = ConnectionAddr::Tcp(host, port);
+                Client::open(info)
+            })
+            .collect::<RedisResult<Vec<_>>>()
+            .map(Some)
+    }
+
+    fn connection(&self) -> RedisResult<Connection> {
+        let conn = self.inner.conn.read().unwrap().clone();
+        match conn {
+            Some(conn) if self.inner.breaker.allow() => Ok(conn),
+            _ => Err(RedisError::from((ErrorKind::IoError, "Redis unavailable"))),
+        }
+    }
+
+    /// Only connectivity failures count against the breaker; command errors
+    /// such as WRONGTYPE mean Redis is up. READONLY counts too: it means we
+    /// are talking to a demoted master after a failover.
+    fn record<T>(&self, result: &RedisResult<T>) {
+        let breaker = &self.inner.breaker;
+        match result {
+            Err(e)
+                if e.is_io_error()
+                    || e.is_timeout()
+                    || e.is_connection_dropped()
+                    || e.is_connection_refusal()
+                    || e.kind() == ErrorKind::ReadOnly =>
+            {
+                let was_open = breaker.is_open();
+                breaker.record_failure();
+                if !was_open && breaker.is_open() {
+                    tracing::warn!("Redis circuit opened after repeated failures: {}", e);
+                }
+            }
+            _ => {
+                if breaker.is_open() {
+                    tracing::info!("Redis circuit closed");
+                }
+                breaker.record_success();
+            }
+        }
+        CACHE_AVAILABLE.set(if breaker.is_open() { 0 } else { 1 });
+    }
+}
+
+impl ConnectionLike for Connection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> BoxFuture<'a, RedisResult<Value>> {
+        match self {
+            Connection::Single(conn) => conn.req_packed_command(cmd),
+            Connection::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> BoxFuture<'a, RedisResult<Vec<Value>>> {
+        match self {
+            Connection::Single(conn) => conn.req_packed_commands(cmd, offset, count),
+            Connection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Connection::Single(conn) => conn.get_db(),
+            Connection::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+impl ConnectionLike for RedisPool {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> BoxFuture<'a, RedisResult<Value>> {
+        let span = telemetry::redis_span(&command_name(cmd));
+        Box::pin(
+            async move {
+                let mut conn = self.connection()?;
+                let result = conn.req_packed_command(cmd).await;
+                self.record(&result);
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> BoxFuture<'a, RedisResult<Vec<Value>>> {
+        let span = telemetry::redis_span("PIPELINE");
+        Box::pin(
+            async move {
+                let mut conn = self.connection()?;
+                let result = conn.req_packed_commands(cmd, offset, count).await;
+                self.record(&result);
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner
+            .conn
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|conn| conn.get_db())
+            .unwrap_or(0)
+    }
+}
+
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        _ => "UNKNOWN".to_string(),
+    }
 }
 
 pub async fn check_redis_connection(pool: &mut RedisPool) -> Result<(), RedisError> {
     redis::cmd("PING").query_async(pool).await
+}
+
+/// TTLs applied to one template's cache entries. A TTL of zero disables
+/// caching at that layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub template_ttl_secs: u64,
+    pub rendered_ttl_secs: u64,
+}
+
+/// Per-template overrides read from `meta.cache`, e.g.
+/// `{"cache": {"rendered_ttl_secs": 0}}` for output that must never be cached.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheOverrides {
+    pub template_ttl_secs: Option<u64>,
+    pub rendered_ttl_secs: Option<u64>,
+}
+
+impl CachePolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            template_ttl_secs: config.template_cache_ttl_secs,
+            rendered_ttl_secs: config.rendered_cache_ttl_secs,
+        }
+    }
+
+    /// Applies the template's overrides. Malformed overrides are rejected
+    /// when the template is created, so here they are simply ignored.
+    /// Templates marked `"sensitive": true` never have output cached.
+    pub fn for_meta(&self, meta: Option<&serde_json::Value>) -> Self {
+        let overrides = parse_cache_overrides(meta).unwrap_or_default();
+        let rendered_ttl_secs = if is_sensitive(meta).unwrap_or(false) {
+            0
+        } else {
+            overrides.rendered_ttl_secs.unwrap_or(self.rendered_ttl_secs)
+        };
+        Self {
+            template_ttl_secs: overrides.template_ttl_secs.unwrap_or(self.template_ttl_secs),
+            rendered_ttl_secs,
+        }
+    }
+}
+
+/// Reads `meta.sensitive`, which marks templates whose output carries
+/// secrets such as OTPs or reset links.
+pub fn is_sensitive(meta: Option<&serde_json::Value>) -> Result<bool, String> {
+    match meta.and_then(|m| m.get("sensitive")) {
+        None | Some(serde_json::Value::Null) => Ok(false),
+        Some(serde_json::Value::Bool(sensitive)) => Ok(*sensitive),
+        Some(_) => Err("meta.sensitive must be a boolean".to_string()),
+    }
+}
+
+pub fn parse_cache_overrides(meta: Option<&serde_json::Value>) -> Result<CacheOverrides, String> {
+    match meta.and_then(|m| m.get("cache")) {
+        None | Some(serde_json::Value::Null) => Ok(CacheOverrides::default()),
+        Some(value) => CacheOverrides::deserialize(value).map_err(|e| format!("Invalid meta.cache: {}", e)),
+    }
+}
+
+/// Cached value with its own freshness deadline, so an entry can be served
+/// stale for a grace period after `fresh_until` while it is refreshed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry<T> {
+    pub fresh_until: i64,
+    pub value: T,
+}
+
+pub enum Cached<T> {
+    Fresh(T),
+    Stale(T),
+}
+
+/// Serializes a value into a `CacheEntry` and returns it along with the
+/// Redis TTL, which extends past freshness by `stale_secs`. Returns `None`
+/// when `ttl_secs` is zero, i.e. the value must not be cached.
+pub fn encode_entry<T: Serialize>(value: &T, ttl_secs: u64, stale_secs: u64) -> Option<(String, u64)> {
+    if ttl_secs == 0 {
+        return None;
+    }
+    let entry = CacheEntry {
+        fresh_until: chrono::Utc::now().timestamp() + ttl_secs as i64,
+        value,
+    };
+    serde_json::to_string(&entry)
+        .ok()
+        .map(|payload| (payload, ttl_secs + stale_secs))
+}
+
+pub fn decode_entry<T: DeserializeOwned>(payload: &str) -> Option<Cached<T>> {
+    let entry: CacheEntry<T> = serde_json::from_str(payload).ok()?;
+    if chrono::Utc::now().timestamp() < entry.fresh_until {
+        Some(Cached::Fresh(entry.value))
+    } else {
+        Some(Cached::Stale(entry.value))
+    }
+}
+
+/// Cache keys embed the template code as a Redis Cluster hash tag
+/// (`{code}`), so a template's `template:`, `rendered:` and
+/// `rendered_index:` keys live in the same slot and can be written and
+/// invalidated together in one pipeline.
+pub fn template_key(template_code: &str, version: Option<i32>, language: &str) -> String {
+    match version {
+        Some(ver) => format!("template:{{{}}}:{}:{}", template_code, ver, language),
+        None => format!("template:{{{}}}:latest:{}", template_code, language),
+    }
+}
+
+/// Must carry the same `{code}` hash tag as `rendered_index_key`:
+/// `INVALIDATE_RENDERED_SCRIPT` deletes these keys without declaring them.
+pub fn rendered_key(template_code: &str, version: i32, language: &str, var_hash: &str) -> String {
+    format!("rendered:{{{}}}:{}:{}:{}", template_code, version, language, var_hash)
+}
+
+/// Set of rendered keys written for one template/version/language, so they
+/// can be invalidated without scanning the keyspace. Its hash tag must match
+/// its members' (see `INVALIDATE_RENDERED_SCRIPT`).
+pub fn rendered_index_key(template_code: &str, version: i32, language: &str) -> String {
+    format!("rendered_index:{{{}}}:{}:{}", template_code, version, language)
+}
+
+/// Stores a rendered payload and records its key in the template's index.
+/// The index TTL is refreshed on every write so it outlives its members.
+pub async fn store_rendered(
+    pool: &mut RedisPool,
+    template_code: &str,
+    version: i32,
+    language: &str,
+    key: &str,
+    value: &str,
+    ttl_secs: u64,
+) -> Result<(), RedisError> {
+    let index_key = rendered_index_key(template_code, version, language);
+
+    redis::pipe()
+        .atomic()
+        .set_ex(key, value, ttl_secs)
+        .ignore()
+        .sadd(&index_key, key)
+        .ignore()
+        .expire(&index_key, ttl_secs as i64)
+        .ignore()
+        .query_async(pool)
+        .await
+}
+
+/// Deletes the members of a rendered index and the index itself in one
+/// step, so an entry stored concurrently is either deleted with the rest or
+/// stays tracked.
+///
+/// Invariant: the members come from SMEMBERS and are not declared in KEYS,
+/// which Redis Cluster only tolerates because they hash to the index's slot.
+/// That holds as long as `rendered_key` and `rendered_index_key` both tag
+/// the template code as `{code}`; changing either key format breaks
+/// invalidation in Cluster mode.
+const INVALIDATE_RENDERED_SCRIPT: &str = r#"
+local keys = redis.call('SMEMBERS', KEYS[1])
+for _, key in ipairs(keys) do
+    redis.call('DEL', key)
+end
+redis.call('DEL', KEYS[1])
+return #keys
+"#;
+
+/// Deletes every rendered entry tracked for the template/version/language
+/// along with the index itself, atomically. Cost is proportional to the
+/// tracked entries.
+pub async fn invalidate_rendered(
+    pool: &mut RedisPool,
+    template_code: &str,
+    version: i32,
+    language: &str,
+) -> Result<(), RedisError> {
+    let index_key = rendered_index_key(template_code, version, language);
+    let _: i64 = redis::Script::new(INVALIDATE_RENDERED_SCRIPT)
+        .key(index_key)
+        .invoke_async(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn rendered_members(
+    pool: &mut RedisPool,
+    template_code: &str,
+    version: i32,
+    language: &str,
+) -> Result<Vec<String>, RedisError> {
+    pool.smembers(rendered_index_key(template_code, version, language)).await
+}
+
+/// Describes the keys that currently exist, with their remaining TTL
+/// (`None` if the key never expires). Missing keys are left out.
+pub async fn describe_keys(pool: &mut RedisPool, keys: &[String]) -> Result<Vec<CacheKeyInfo>, RedisError> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.ttl(key);
+    }
+    let ttls: Vec<i64> = pipe.query_async(pool).await?;
+
+    Ok(keys
+        .iter()
+        .zip(ttls)
+        .filter(|(_, ttl)| *ttl != -2)
+        .map(|(key, ttl)| CacheKeyInfo {
+            key: key.clone(),
+            ttl_secs: (ttl >= 0).then_some(ttl),
+        })
+        .collect())
+}
+
+/// Deletes every key matching `pattern` and returns them. Uses SCAN so the
+/// server is never blocked the way KEYS would; in cluster mode every
+/// primary is scanned directly.
+pub async fn delete_matching(pool: &mut RedisPool, pattern: &str) -> Result<Vec<String>, RedisError> {
+    let Some(primaries) = pool.cluster_primaries().await? else {
+        return scan_keys(pool, pattern, true).await;
+    };
+
+    let mut deleted = Vec::new();
+    for client in primaries {
+        let mut conn = client.get_multiplexed_async_connection().await?;
+        deleted.extend(scan_keys(&mut conn, pattern, true).await?);
+    }
+    Ok(deleted)
+}
+
+/// Every key matching `pattern`, found with SCAN on every primary.
+pub async fn keys_matching(pool: &mut RedisPool, pattern: &str) -> Result<Vec<String>, RedisError> {
+    let Some(primaries) = pool.cluster_primaries().await? else {
+        return scan_keys(pool, pattern, false).await;
+    };
+
+    let mut keys = Vec::new();
+    for client in primaries {
+        let mut conn = client.get_multiplexed_async_connection().await?;
+        keys.extend(scan_keys(&mut conn, pattern, false).await?);
+    }
+    Ok(keys)
+}
+
+/// SCANs one connection, deleting each page of matches when `delete` is
+/// set. Keys are deleted one command each because matches span hash slots.
+async fn scan_keys<C: ConnectionLike + Send>(
+    conn: &mut C,
+    pattern: &str,
+    delete: bool,
+) -> Result<Vec<String>, RedisError> {
+    let mut cursor: u64 = 0;
+    let mut found = Vec::new();
+
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(500)
+            .query_async(conn)
+            .await?;
+
+        if delete && !keys.is_empty() {
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.del(key).ignore();
+            }
+            let _: () = pipe.query_async(conn).await?;
+        }
+        found.extend(keys);
+        if next == 0 {
+            return Ok(found);
+        }
+        cursor = next;
+    }
+}
+
+pub async fn publish_invalidation(pool: &mut RedisPool, keys: &[String]) -> Result<(), RedisError> {
+    let payload = serde_json::to_string(keys).unwrap_or_default();
+    pool.publish(INVALIDATION_CHANNEL, payload).await
+}
+
+/// Subscribes to the invalidation channel for the lifetime of the process,
+/// reconnecting with a fixed delay. `on_resubscribe` runs after every
+/// (re)subscription because messages published while disconnected are lost.
+pub fn spawn_invalidation_listener<F, R>(pool: RedisPool, on_keys: F, on_resubscribe: R)
+where
+    F: Fn(Vec<String>) + Send + Sync + 'static,
+    R: Fn() + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match subscribe_invalidations(&pool, &on_keys, &on_resubscribe).await {
+                Ok(()) => tracing::warn!("Invalidation subscription closed, reconnecting"),
+                Err(e) => tracing::warn!("Invalidation subscription failed: {}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+async fn subscribe_invalidations<F, R>(pool: &RedisPool, on_keys: &F, on_resubscribe: &R) -> Result<(), RedisError>
+where
+    F: Fn(Vec<String>) + Sync,
+    R: Fn() + Sync,
+{
+    let mut pubsub = pool.pubsub().await?;
+    pubsub.subscribe(INVALIDATION_CHANNEL).await?;
+    on_resubscribe();
+
+    let mut messages = pubsub.on_message();
+    while let Some(msg) = messages.next().await {
+        let payload: String = msg.get_payload()?;
+        match serde_json::from_str::<Vec<String>>(&payload) {
+            Ok(keys) => on_keys(keys),
+            Err(e) => tracing::warn!("Ignoring malformed invalidation message: {}", e),
+        }
+    }
+
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive-failure circuit breaker. After `threshold` failures the
+/// circuit opens and calls are skipped for `cooldown`; then a single probe
+/// is let through, and its outcome closes or re-opens the circuit.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    probe_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a call may proceed. While half-open only one probe is in
+    /// flight at a time; a probe that never reports back (e.g. cancelled)
+    /// is superseded after another cooldown.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(open_until) = state.open_until else {
+            return true;
+        };
+
+        let now = Instant::now();
+        if now < open_until {
+            return false;
+        }
+        match state.probe_started {
+            Some(started) if now.duration_since(started) < self.cooldown => false,
+            _ => {
+                state.probe_started = Some(now);
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = state.failures.saturating_add(1);
+        if state.probe_started.is_some() || state.failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            state.probe_started = None;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().open_until.is_some()
+    }
+}
@@ -1,53 +1,348 @@
+use crate::middleware::auth::scopes;
+use std::collections::HashMap;
 use std::env;
+use std::net::IpAddr;
+
+/// How the service reaches Redis.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedisMode {
+    Standalone,
+    Cluster,
+    Sentinel,
+}
+
+impl RedisMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standalone" => Some(RedisMode::Standalone),
+            "cluster" => Some(RedisMode::Cluster),
+            "sentinel" => Some(RedisMode::Sentinel),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub database_url: String,
     pub redis_url: String,
+    pub redis_mode: RedisMode,
+    pub redis_cluster_nodes: Vec<String>,
+    pub redis_sentinel_nodes: Vec<String>,
+    pub redis_sentinel_master: String,
+    pub redis_timeout_ms: u64,
+    pub redis_breaker_threshold: u32,
+    pub redis_breaker_cooldown_secs: u64,
+    pub readiness_require_redis: bool,
     pub server_host: String,
     pub server_port: u16,
-    pub jwt_secret: String,
+    pub jwt_secret: Option<String>,
+    pub jwks_url: Option<String>,
+    pub jwks_refresh_secs: u64,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Vec<String>,
+    pub auth_require_read: bool,
+    pub auth_protect_ops: bool,
+    pub hmac_auth_enabled: bool,
+    pub hmac_timestamp_tolerance_secs: u64,
+    pub hmac_scopes: Vec<String>,
+    pub rate_limit_enabled: bool,
+    pub rate_limit_window_secs: u64,
+    pub rate_limit_default: u64,
+    pub rate_limit_routes: HashMap<String, u64>,
+    pub rate_limit_clients: HashMap<String, u64>,
+    pub rate_limit_auth_failures: u64,
+    pub rate_limit_trusted_proxies: Vec<IpAddr>,
     pub secret_key: String,
     pub max_rendered_size_kb: usize,
     pub template_cache_ttl_secs: u64,
     pub rendered_cache_ttl_secs: u64,
+    pub rendered_cache_encryption_enabled: bool,
+    pub cache_stale_while_revalidate_secs: u64,
+    pub template_local_cache_capacity: usize,
+    pub template_local_cache_ttl_secs: u64,
+    pub compiled_cache_max_entries: usize,
+    pub compiled_cache_max_bytes: usize,
+    pub cache_warmup_enabled: bool,
+    pub usage_tracking_enabled: bool,
+    pub usage_flush_interval_secs: u64,
+    pub otel_exporter_endpoint: Option<String>,
+    pub otel_service_name: String,
+    pub otel_sampling_ratio: f64,
+    pub webhook_worker_enabled: bool,
+    pub webhook_poll_interval_secs: u64,
+    pub webhook_batch_size: i64,
+    pub webhook_max_attempts: i32,
+    pub webhook_backoff_base_secs: u64,
+    pub webhook_backoff_max_secs: u64,
+    pub webhook_timeout_secs: u64,
+    pub webhook_allow_private_targets: bool,
+    pub outbox_dispatcher_enabled: bool,
+    pub outbox_poll_interval_secs: u64,
+    pub outbox_batch_size: i64,
+    pub outbox_stream_key: String,
+    pub outbox_stream_maxlen: u64,
+    pub outbox_retention_hours: i32,
 }
 
 impl Config {
     pub fn from_env() -> Self {
         dotenv::dotenv().ok();
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Builds the config from `lookup` instead of the process environment,
+    /// so tests can describe one without mutating global state.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |key: &str| lookup(key).ok_or(env::VarError::NotPresent);
 
-        Self {
-            database_url: env::var("DATABASE_URL")
+        let config = Self {
+            database_url: var("DATABASE_URL")
                 .expect("DATABASE_URL must be set"),
-            redis_url: env::var("REDIS_URL")
+            redis_url: var("REDIS_URL")
                 .expect("REDIS_URL must be set"),
-            server_host: env::var("SERVER_HOST")
+            redis_mode: RedisMode::parse(&var("REDIS_MODE").unwrap_or_else(|_| "standalone".to_string()))
+                .expect("REDIS_MODE must be standalone, cluster or sentinel"),
+            redis_cluster_nodes: var("REDIS_CLUSTER_NODES")
+                .map(|s| split_list(&s))
+                .unwrap_or_default(),
+            redis_sentinel_nodes: var("REDIS_SENTINEL_NODES")
+                .map(|s| split_list(&s))
+                .unwrap_or_default(),
+            redis_sentinel_master: var("REDIS_SENTINEL_MASTER")
+                .unwrap_or_else(|_| "mymaster".to_string()),
+            redis_timeout_ms: var("REDIS_TIMEOUT_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("REDIS_TIMEOUT_MS must be a valid number"),
+            redis_breaker_threshold: var("REDIS_BREAKER_THRESHOLD")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("REDIS_BREAKER_THRESHOLD must be a valid number"),
+            redis_breaker_cooldown_secs: var("REDIS_BREAKER_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("REDIS_BREAKER_COOLDOWN_SECS must be a valid number"),
+            readiness_require_redis: var("READINESS_REQUIRE_REDIS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("READINESS_REQUIRE_REDIS must be true or false"),
+            server_host: var("SERVER_HOST")
                 .unwrap_or_else(|_| "0.0.0.0".to_string()),
-            server_port: env::var("SERVER_PORT")
+            server_port: var("SERVER_PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .expect("SERVER_PORT must be a valid u16"),
-            jwt_secret: env::var("JWT_SECRET")
-                .expect("JWT_SECRET must be set"),
-            secret_key: env::var("SECRET_KEY")
+            jwt_secret: var("JWT_SECRET").ok().filter(|s| !s.is_empty()),
+            jwks_url: var("JWKS_URL").ok().filter(|s| !s.is_empty()),
+            jwks_refresh_secs: var("JWKS_REFRESH_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .expect("JWKS_REFRESH_SECS must be a valid number"),
+            jwt_issuer: var("JWT_ISSUER").ok().filter(|s| !s.is_empty()),
+            jwt_audience: var("JWT_AUDIENCE")
+                .map(|s| split_list(&s))
+                .unwrap_or_default(),
+            auth_require_read: var("AUTH_REQUIRE_READ")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("AUTH_REQUIRE_READ must be true or false"),
+            auth_protect_ops: var("AUTH_PROTECT_OPS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("AUTH_PROTECT_OPS must be true or false"),
+            hmac_auth_enabled: var("HMAC_AUTH_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("HMAC_AUTH_ENABLED must be true or false"),
+            hmac_timestamp_tolerance_secs: var("HMAC_TIMESTAMP_TOLERANCE_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .expect("HMAC_TIMESTAMP_TOLERANCE_SECS must be a valid number"),
+            hmac_scopes: split_list(
+                &var("HMAC_SCOPES").unwrap_or_else(|_| "templates:read,templates:write".to_string()),
+            ),
+            rate_limit_enabled: var("RATE_LIMIT_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("RATE_LIMIT_ENABLED must be true or false"),
+            rate_limit_window_secs: var("RATE_LIMIT_WINDOW_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .expect("RATE_LIMIT_WINDOW_SECS must be a valid number"),
+            rate_limit_default: var("RATE_LIMIT_DEFAULT")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .expect("RATE_LIMIT_DEFAULT must be a valid number"),
+            rate_limit_routes: parse_limits(&var("RATE_LIMIT_ROUTES").unwrap_or_default())
+                .expect("RATE_LIMIT_ROUTES must be a list of route=limit pairs"),
+            rate_limit_clients: parse_limits(&var("RATE_LIMIT_CLIENTS").unwrap_or_default())
+                .expect("RATE_LIMIT_CLIENTS must be a list of kind:subject=limit pairs"),
+            rate_limit_auth_failures: var("RATE_LIMIT_AUTH_FAILURES")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("RATE_LIMIT_AUTH_FAILURES must be a valid number"),
+            rate_limit_trusted_proxies: split_list(&var("RATE_LIMIT_TRUSTED_PROXIES").unwrap_or_default())
+                .iter()
+                .map(|ip| ip.parse())
+                .collect::<Result<_, _>>()
+                .expect("RATE_LIMIT_TRUSTED_PROXIES must be a list of IP addresses"),
+            secret_key: var("SECRET_KEY")
                 .expect("SECRET_KEY must be set"),    
-            max_rendered_size_kb: env::var("MAX_RENDERED_SIZE_KB")
+            max_rendered_size_kb: var("MAX_RENDERED_SIZE_KB")
                 .unwrap_or_else(|_| "64".to_string())
                 .parse()
                 .expect("MAX_RENDERED_SIZE_KB must be a valid number"),
-            template_cache_ttl_secs: env::var("TEMPLATE_CACHE_TTL_SECS")
+            template_cache_ttl_secs: var("TEMPLATE_CACHE_TTL_SECS")
                 .unwrap_or_else(|_| "3600".to_string())
                 .parse()
                 .expect("TEMPLATE_CACHE_TTL_SECS must be a valid number"),
-            rendered_cache_ttl_secs: env::var("RENDERED_CACHE_TTL_SECS")
+            rendered_cache_ttl_secs: var("RENDERED_CACHE_TTL_SECS")
                 .unwrap_or_else(|_| "300".to_string())
                 .parse()
                 .expect("RENDERED_CACHE_TTL_SECS must be a valid number"),
+            rendered_cache_encryption_enabled: var("RENDERED_CACHE_ENCRYPTION_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("RENDERED_CACHE_ENCRYPTION_ENABLED must be true or false"),
+            cache_stale_while_revalidate_secs: var("CACHE_STALE_WHILE_REVALIDATE_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .expect("CACHE_STALE_WHILE_REVALIDATE_SECS must be a valid number"),
+            template_local_cache_capacity: var("TEMPLATE_LOCAL_CACHE_CAPACITY")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("TEMPLATE_LOCAL_CACHE_CAPACITY must be a valid number"),
+            template_local_cache_ttl_secs: var("TEMPLATE_LOCAL_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("TEMPLATE_LOCAL_CACHE_TTL_SECS must be a valid number"),
+            compiled_cache_max_entries: var("COMPILED_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("COMPILED_CACHE_MAX_ENTRIES must be a valid number"),
+            compiled_cache_max_bytes: var("COMPILED_CACHE_MAX_BYTES")
+                .unwrap_or_else(|_| "67108864".to_string())
+                .parse()
+                .expect("COMPILED_CACHE_MAX_BYTES must be a valid number"),
+            cache_warmup_enabled: var("CACHE_WARMUP_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("CACHE_WARMUP_ENABLED must be true or false"),
+            usage_tracking_enabled: var("USAGE_TRACKING_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("USAGE_TRACKING_ENABLED must be true or false"),
+            usage_flush_interval_secs: var("USAGE_FLUSH_INTERVAL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .expect("USAGE_FLUSH_INTERVAL_SECS must be a valid number"),
+            otel_exporter_endpoint: var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|s| !s.is_empty()),
+            otel_service_name: var("OTEL_SERVICE_NAME")
+                .unwrap_or_else(|_| "templates-service".to_string()),
+            otel_sampling_ratio: var("OTEL_TRACES_SAMPLER_RATIO")
+                .unwrap_or_else(|_| "1.0".to_string())
+                .parse()
+                .expect("OTEL_TRACES_SAMPLER_RATIO must be a number between 0 and 1"),
+            webhook_worker_enabled: var("WEBHOOK_WORKER_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("WEBHOOK_WORKER_ENABLED must be true or false"),
+            webhook_poll_interval_secs: var("WEBHOOK_POLL_INTERVAL_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("WEBHOOK_POLL_INTERVAL_SECS must be a valid number"),
+            webhook_batch_size: var("WEBHOOK_BATCH_SIZE")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .expect("WEBHOOK_BATCH_SIZE must be a valid number"),
+            webhook_max_attempts: var("WEBHOOK_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .expect("WEBHOOK_MAX_ATTEMPTS must be a valid number"),
+            webhook_backoff_base_secs: var("WEBHOOK_BACKOFF_BASE_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("WEBHOOK_BACKOFF_BASE_SECS must be a valid number"),
+            webhook_backoff_max_secs: var("WEBHOOK_BACKOFF_MAX_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .expect("WEBHOOK_BACKOFF_MAX_SECS must be a valid number"),
+            webhook_timeout_secs: var("WEBHOOK_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("WEBHOOK_TIMEOUT_SECS must be a valid number"),
+            webhook_allow_private_targets: var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("WEBHOOK_ALLOW_PRIVATE_TARGETS must be true or false"),
+            outbox_dispatcher_enabled: var("OUTBOX_DISPATCHER_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .expect("OUTBOX_DISPATCHER_ENABLED must be true or false"),
+            outbox_poll_interval_secs: var("OUTBOX_POLL_INTERVAL_SECS")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .expect("OUTBOX_POLL_INTERVAL_SECS must be a valid number"),
+            outbox_batch_size: var("OUTBOX_BATCH_SIZE")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .expect("OUTBOX_BATCH_SIZE must be a valid number"),
+            outbox_stream_key: var("OUTBOX_STREAM_KEY")
+                .unwrap_or_else(|_| "templates:events".to_string()),
+            outbox_stream_maxlen: var("OUTBOX_STREAM_MAXLEN")
+                .unwrap_or_else(|_| "100000".to_string())
+                .parse()
+                .expect("OUTBOX_STREAM_MAXLEN must be a valid number"),
+            outbox_retention_hours: var("OUTBOX_RETENTION_HOURS")
+                .unwrap_or_else(|_| "168".to_string())
+                .parse()
+                .expect("OUTBOX_RETENTION_HOURS must be a valid number"),
+        };
+
+        if config.redis_mode == RedisMode::Sentinel && config.redis_sentinel_nodes.is_empty() {
+            panic!("REDIS_SENTINEL_NODES must be set when REDIS_MODE=sentinel");
+        }
+
+        if config.jwt_secret.is_none() && config.jwks_url.is_none() {
+            panic!("Either JWT_SECRET or JWKS_URL must be set");
+        }
+
+        // A leaked shared secret must never be able to manage API keys.
+        if config.hmac_scopes.iter().any(|scope| scope == scopes::ADMIN) {
+            panic!("HMAC_SCOPES must not include {}", scopes::ADMIN);
         }
+
+        config
     }
 
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server_host, self.server_port)
     }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `name=limit` pairs separated by commas, e.g. `render_template=300,create_template=30`.
+pub fn parse_limits(value: &str) -> Result<HashMap<String, u64>, String> {
+    split_list(value)
+        .into_iter()
+        .map(|pair| {
+            let (name, limit) = pair
+                .rsplit_once('=')
+                .ok_or_else(|| format!("missing '=' in '{}'", pair))?;
+            let limit = limit
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid limit in '{}'", pair))?;
+            Ok((name.trim().to_string(), limit))
+        })
+        .collect()
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+const ENCRYPTED_PREFIX: &str = "enc1:";
+const KEY_INFO: &[u8] = b"templates-service rendered-cache v1";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM for cache payloads, keyed by HKDF-SHA256 over `SECRET_KEY`.
+/// Each payload is bound to its cache key as associated data, so an entry
+/// copied under another key fails to decrypt.
+pub struct PayloadCipher {
+    cipher: Aes256Gcm,
+}
+
+impl PayloadCipher {
+    pub fn from_secret(secret: &str) -> Self {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, secret.as_bytes())
+            .expand(KEY_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self {
+            cipher: Aes256Gcm::new(&key.into()),
+        }
+    }
+
+    /// Returns `enc1:` followed by hex of nonce || ciphertext.
+    pub fn encrypt(&self, cache_key: &str, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: cache_key.as_bytes() })
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        format!("{}{}", ENCRYPTED_PREFIX, hex::encode(sealed))
+    }
+
+    /// `None` for anything not produced by `encrypt` under this key and
+    /// cache key, including plaintext entries written before encryption
+    /// was enabled.
+    pub fn decrypt(&self, cache_key: &str, payload: &str) -> Option<String> {
+        let sealed = hex::decode(payload.strip_prefix(ENCRYPTED_PREFIX)?).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::from(<[u8; NONCE_LEN]>::try_from(nonce).ok()?);
+        let plaintext = self
+            .cipher
+            .decrypt(&nonce, Payload { msg: ciphertext, aad: cache_key.as_bytes() })
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+}
@@ -1,3 +1,5 @@
+use crate::middleware::current_request_id;
+use crate::models::{ErrorResponse, TemplateErrorDetails};
 use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use std::fmt;
 
@@ -6,10 +8,16 @@ pub enum AppError {
     DatabaseError(sqlx::Error),
     RedisError(redis::RedisError),
     TemplateNotFound,
-    RenderError(String),
+    ApiKeyNotFound,
+    WebhookNotFound,
+    InvalidScope(String),
+    Forbidden(String),
+    RenderError(Box<TemplateErrorDetails>),
+    TemplateSyntaxError(Box<TemplateErrorDetails>),
     InvalidTemplateType,
     InvalidContent(String),
     RenderedSizeExceeded,
+    RateLimited { retry_after_secs: u64 },
     InternalError(String),
 }
 
@@ -19,48 +27,171 @@ impl fmt::Display for AppError {
             AppError::DatabaseError(e) => write!(f, "Database error: {}", e),
             AppError::RedisError(e) => write!(f, "Cache error: {}", e),
             AppError::TemplateNotFound => write!(f, "Template not found"),
-            AppError::RenderError(msg) => write!(f, "Render error: {}", msg),
+            AppError::ApiKeyNotFound => write!(f, "API key not found"),
+            AppError::WebhookNotFound => write!(f, "Webhook subscription not found"),
+            AppError::InvalidScope(msg) => write!(f, "Invalid scope: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::RenderError(details) => write!(f, "Render error: {}", details.message),
+            AppError::TemplateSyntaxError(details) => match (details.line, details.column) {
+                (Some(line), Some(column)) => write!(
+                    f,
+                    "Template syntax error at line {}, column {}: {}",
+                    line, column, details.message
+                ),
+                _ => write!(f, "Template syntax error: {}", details.message),
+            },
             AppError::InvalidTemplateType => write!(f, "Invalid template type"),
             AppError::InvalidContent(msg) => write!(f, "Invalid content: {}", msg),
             AppError::RenderedSizeExceeded => write!(f, "Rendered size exceeded limit"),
+            AppError::RateLimited { retry_after_secs } => {
+                write!(f, "Rate limit exceeded, retry after {} seconds", retry_after_secs)
+            }
             AppError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
 }
 
+impl AppError {
+    /// A copy of the error, except for database and cache errors, whose
+    /// sources cannot be cloned.
+    pub fn try_clone(&self) -> Option<AppError> {
+        Some(match self {
+            AppError::DatabaseError(_) | AppError::RedisError(_) => return None,
+            AppError::TemplateNotFound => AppError::TemplateNotFound,
+            AppError::ApiKeyNotFound => AppError::ApiKeyNotFound,
+            AppError::WebhookNotFound => AppError::WebhookNotFound,
+            AppError::InvalidScope(msg) => AppError::InvalidScope(msg.clone()),
+            AppError::Forbidden(msg) => AppError::Forbidden(msg.clone()),
+            AppError::RenderError(details) => AppError::RenderError(details.clone()),
+            AppError::TemplateSyntaxError(details) => AppError::TemplateSyntaxError(details.clone()),
+            AppError::InvalidTemplateType => AppError::InvalidTemplateType,
+            AppError::InvalidContent(msg) => AppError::InvalidContent(msg.clone()),
+            AppError::RenderedSizeExceeded => AppError::RenderedSizeExceeded,
+            AppError::RateLimited { retry_after_secs } => AppError::RateLimited { retry_after_secs: *retry_after_secs },
+            AppError::InternalError(msg) => AppError::InternalError(msg.clone()),
+        })
+    }
+
+    /// Stable machine-readable code returned as `error`.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::DatabaseError(e) => match classify_db_error(e) {
+                BackendFailure::Conflict => "conflict",
+                BackendFailure::Unavailable => "db_unavailable",
+                BackendFailure::Other => "db_error",
+            },
+            AppError::RedisError(e) => match classify_redis_error(e) {
+                BackendFailure::Unavailable => "cache_unavailable",
+                _ => "cache_error",
+            },
+            AppError::TemplateNotFound => "template_not_found",
+            AppError::ApiKeyNotFound => "api_key_not_found",
+            AppError::WebhookNotFound => "webhook_not_found",
+            AppError::InvalidScope(_) => "invalid_scope",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::RenderError(_) => "render_error",
+            AppError::TemplateSyntaxError(_) => "template_syntax_error",
+            AppError::InvalidTemplateType => "invalid_template_type",
+            AppError::InvalidContent(_) => "invalid_content",
+            AppError::RenderedSizeExceeded => "rendered_size_exceeded",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::InternalError(_) => "internal_error",
+        }
+    }
+
+    /// Message safe to return to callers. Database, cache and internal
+    /// errors are summarised; their `Display` (driver messages, SQL
+    /// constraint names, hosts) is only logged.
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::DatabaseError(e) => match classify_db_error(e) {
+                BackendFailure::Conflict => "The resource already exists or was modified concurrently".to_string(),
+                BackendFailure::Unavailable => "Database temporarily unavailable, please retry".to_string(),
+                BackendFailure::Other => "A database error occurred".to_string(),
+            },
+            AppError::RedisError(e) => match classify_redis_error(e) {
+                BackendFailure::Unavailable => "Cache temporarily unavailable, please retry".to_string(),
+                _ => "A cache error occurred".to_string(),
+            },
+            AppError::InternalError(_) => "Internal server error".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// How a database or cache failure is reported to callers.
+enum BackendFailure {
+    Conflict,
+    Unavailable,
+    Other,
+}
+
+fn classify_db_error(err: &sqlx::Error) -> BackendFailure {
+    match err {
+        sqlx::Error::Database(e) if e.is_unique_violation() => BackendFailure::Conflict,
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => BackendFailure::Unavailable,
+        _ => BackendFailure::Other,
+    }
+}
+
+fn classify_redis_error(err: &redis::RedisError) -> BackendFailure {
+    if err.is_timeout() || err.is_connection_refusal() || err.is_connection_dropped() || err.is_io_error() {
+        BackendFailure::Unavailable
+    } else {
+        BackendFailure::Other
+    }
+}
+
 impl ResponseError for AppError {
     fn status_code(&self) -> StatusCode {
         match self {
-            AppError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::RedisError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::DatabaseError(e) => match classify_db_error(e) {
+                BackendFailure::Conflict => StatusCode::CONFLICT,
+                BackendFailure::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                BackendFailure::Other => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AppError::RedisError(e) => match classify_redis_error(e) {
+                BackendFailure::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
             AppError::TemplateNotFound => StatusCode::NOT_FOUND,
+            AppError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+            AppError::WebhookNotFound => StatusCode::NOT_FOUND,
+            AppError::InvalidScope(_) => StatusCode::BAD_REQUEST,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::RenderError(_) => StatusCode::BAD_REQUEST,
+            AppError::TemplateSyntaxError(_) => StatusCode::BAD_REQUEST,
             AppError::InvalidTemplateType => StatusCode::BAD_REQUEST,
             AppError::InvalidContent(_) => StatusCode::BAD_REQUEST,
             AppError::RenderedSizeExceeded => StatusCode::BAD_REQUEST,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
             AppError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        let error_code = match self {
-            AppError::DatabaseError(_) => "db_error",
-            AppError::RedisError(_) => "cache_error",
-            AppError::TemplateNotFound => "template_not_found",
-            AppError::RenderError(_) => "render_error",
-            AppError::InvalidTemplateType => "invalid_template_type",
-            AppError::InvalidContent(_) => "invalid_content",
-            AppError::RenderedSizeExceeded => "rendered_size_exceeded",
-            AppError::InternalError(_) => "internal_error",
-        };
+        let status = self.status_code();
+        let error_code = self.error_code();
+        let request_id = current_request_id();
 
-        HttpResponse::build(self.status_code()).json(serde_json::json!({
-            "success": false,
-            "data": serde_json::Value::Null,
-            "error": error_code,
-            "message": self.to_string(),
-            "meta": serde_json::Value::Null,
-        }))
+        // The full cause stays server-side, tagged with the request id the
+        // caller sees in the response.
+        if status.is_server_error() {
+            tracing::error!(error_code, request_id = request_id.as_deref(), "Request failed: {}", self);
+        } else if status == StatusCode::CONFLICT {
+            tracing::warn!(error_code, request_id = request_id.as_deref(), "Request conflicted: {}", self);
+        }
+
+        let mut builder = HttpResponse::build(status);
+        if let AppError::RateLimited { retry_after_secs } = self {
+            builder.insert_header(("Retry-After", retry_after_secs.to_string()));
+        }
+
+        let mut body = ErrorResponse::new(error_code, self.public_message());
+        if let AppError::RenderError(details) | AppError::TemplateSyntaxError(details) = self {
+            body.details = Some(details.as_ref().clone());
+        }
+        builder.json(body)
     }
 }
 
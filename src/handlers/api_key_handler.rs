@@ -0,0 +1,62 @@
+use crate::error::AppError;
+use crate::middleware::auth::Principal;
+use crate::middleware::current_request_id;
+use crate::models::{ApiKeyResponse, ApiResponse, CreateApiKeyRequest, CreatedApiKeyResponse};
+use crate::services::ApiKeyService;
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+pub async fn create_api_key(
+    service: web::Data<ApiKeyService>,
+    principal: web::ReqData<Principal>,
+    req: web::Json<CreateApiKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (api_key, plaintext) = service.create_key(req.into_inner(), principal.tenant.as_deref()).await?;
+
+    let response = ApiResponse::success(
+        CreatedApiKeyResponse {
+            api_key: ApiKeyResponse::from(api_key),
+            key: plaintext,
+        },
+        "API key created successfully; store the key now, it will not be shown again"
+    );
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+pub async fn list_api_keys(
+    service: web::Data<ApiKeyService>,
+    principal: web::ReqData<Principal>,
+) -> Result<HttpResponse, AppError> {
+    let keys = service.list_keys(principal.tenant.as_deref()).await?;
+
+    let responses: Vec<ApiKeyResponse> = keys.into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    let response = ApiResponse::success(
+        responses,
+        "API keys retrieved successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn revoke_api_key(
+    service: web::Data<ApiKeyService>,
+    principal: web::ReqData<Principal>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    service.revoke_key(path.into_inner(), principal.tenant.as_deref()).await?;
+
+    let response: ApiResponse<()> = ApiResponse {
+        success: true,
+        data: None,
+        error: None,
+        message: "API key revoked successfully".to_string(),
+        meta: None,
+        request_id: current_request_id(),
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use crate::error::AppError;
+use crate::models::{
+    ApiResponse, CacheFlushResponse, CacheInspectionResponse, CachePurgeResponse, TemplateCacheState,
+};
+use crate::services::{RenderService, TemplateService};
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CachePurgeQuery {
+    pub version: Option<i32>,
+    pub language: Option<String>,
+}
+
+pub async fn inspect_template_cache(
+    template_service: web::Data<TemplateService>,
+    render_service: web::Data<RenderService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let template_code = path.into_inner();
+
+    let templates = template_service.get_versions(&template_code).await?;
+    if templates.is_empty() {
+        return Err(AppError::TemplateNotFound);
+    }
+
+    let mut languages: Vec<&str> = templates.iter().map(|t| t.language.as_str()).collect();
+    languages.sort_unstable();
+    languages.dedup();
+
+    let mut latest_keys = Vec::new();
+    for language in languages {
+        if let Some(info) = template_service.cached_template_key(&template_code, None, language).await? {
+            latest_keys.push(info);
+        }
+    }
+
+    let mut versions = Vec::with_capacity(templates.len());
+    for template in &templates {
+        versions.push(TemplateCacheState {
+            version: template.version,
+            language: template.language.clone(),
+            is_active: template.is_active,
+            template_key: template_service
+                .cached_template_key(&template_code, Some(template.version), &template.language)
+                .await?,
+            rendered_keys: render_service.cached_rendered_keys(template).await?,
+            compiled: render_service.is_compiled(template),
+        });
+    }
+
+    let response = ApiResponse::success(
+        CacheInspectionResponse {
+            template_code,
+            latest_keys,
+            versions,
+        },
+        "Cache state retrieved successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn purge_template_cache(
+    template_service: web::Data<TemplateService>,
+    render_service: web::Data<RenderService>,
+    path: web::Path<String>,
+    query: web::Query<CachePurgeQuery>,
+) -> Result<HttpResponse, AppError> {
+    let template_code = path.into_inner();
+
+    let purged = template_service
+        .purge_cache(&template_code, query.version, query.language.as_deref())
+        .await?;
+    for template in &purged {
+        render_service.evict_compiled(template);
+    }
+
+    let response = ApiResponse::success(
+        CachePurgeResponse {
+            purged_versions: purged.len(),
+        },
+        "Template cache purged successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn flush_cache(
+    template_service: web::Data<TemplateService>,
+    render_service: web::Data<RenderService>,
+) -> Result<HttpResponse, AppError> {
+    let template_keys = template_service.flush_cache().await?;
+    let (rendered_keys, compiled_templates) = render_service.flush_cache().await?;
+
+    let response = ApiResponse::success(
+        CacheFlushResponse {
+            template_keys,
+            rendered_keys,
+            compiled_templates,
+        },
+        "Cache flushed; compiled templates were dropped on this instance only"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
\ No newline at end of file
@@ -1,21 +1,35 @@
 use crate::cache::RedisPool;
+use crate::config::Config;
 use crate::db::DbPool;
 use actix_web::{web, HttpResponse};
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
-struct HealthResponse {
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    #[schema(example = "ok")]
     status: String,
     version: String,
 }
 
-#[derive(Serialize)]
-struct ReadyResponse {
+#[derive(Serialize, ToSchema)]
+pub struct ReadyResponse {
+    /// `ready`, `degraded` (Redis down but optional) or `not_ready`.
+    #[schema(example = "ready")]
     status: String,
+    #[schema(example = "connected")]
     database: String,
+    #[schema(example = "connected")]
     redis: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "operations",
+    responses((status = 200, description = "Process is up", body = HealthResponse)),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn health() -> HttpResponse {
     let response = HealthResponse {
         status: "ok".to_string(),
@@ -24,9 +38,20 @@ pub async fn health() -> HttpResponse {
     HttpResponse::Ok().json(response)
 }
 
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Ready or degraded", body = ReadyResponse),
+        (status = 503, description = "Not ready", body = ReadyResponse),
+    ),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn ready(
     db_pool: web::Data<DbPool>,
     redis_pool: web::Data<RedisPool>,
+    config: web::Data<Config>,
 ) -> HttpResponse {
     let db_status = match crate::db::check_connection(&db_pool).await {
         Ok(_) => "connected",
@@ -39,10 +64,12 @@ pub async fn ready(
         Err(_) => "disconnected",
     };
 
-    let overall_status = if db_status == "connected" && redis_status == "connected" {
-        "ready"
-    } else {
-        "not_ready"
+    // Redis is only a cache: unless configured as required, losing it
+    // degrades the service rather than taking it out of rotation.
+    let overall_status = match (db_status, redis_status) {
+        ("connected", "connected") => "ready",
+        ("connected", _) if !config.readiness_require_redis => "degraded",
+        _ => "not_ready",
     };
 
     let response = ReadyResponse {
@@ -51,7 +78,7 @@ pub async fn ready(
         redis: redis_status.to_string(),
     };
 
-    if overall_status == "ready" {
+    if overall_status != "not_ready" {
         HttpResponse::Ok().json(response)
     } else {
         HttpResponse::ServiceUnavailable().json(response)
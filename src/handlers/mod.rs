@@ -1,5 +1,13 @@
+pub mod api_key_handler;
+pub mod cache_handler;
 pub mod template_handler;
 pub mod health_handler;
+pub mod usage_handler;
+pub mod webhook_handler;
 
+pub use api_key_handler::*;
+pub use cache_handler::*;
 pub use template_handler::*;
-pub use health_handler::*;
\ No newline at end of file
+pub use health_handler::*;
+pub use usage_handler::*;
+pub use webhook_handler::*;
\ No newline at end of file
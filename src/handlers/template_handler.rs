@@ -1,33 +1,57 @@
 use crate::error::AppError;
-use crate::models::{ApiResponse, CreateTemplateRequest, TemplateResponse, TemplateType};
-use crate::services::{RenderService, TemplateService};
+use crate::middleware::current_request_id;
+use crate::models::{ApiResponse, CreateTemplateRequest, TemplateResponse, UsageSummary};
+use crate::services::{CacheWarmer, RenderService, TemplateService, UsageService};
 use actix_web::{web, HttpResponse};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct GetTemplateQuery {
+    /// Defaults to `en`.
     pub language: Option<String>,
+    /// Defaults to the latest active version.
     pub version: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct RenderQuery {
+    /// Defaults to `en`.
     pub language: Option<String>,
+    /// Defaults to the latest active version.
     pub version: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RenderRequest {
+    #[schema(value_type = Object, example = json!({ "name": "Ada" }))]
     pub variables: HashMap<String, Value>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/templates/",
+    tag = "templates",
+    request_body = CreateTemplateRequest,
+    responses(
+        (status = 201, description = "Template version created", body = TemplateApiResponse),
+        (status = 400, description = "Invalid template; syntax errors carry `details` with line and column", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid credentials", body = ErrorResponse),
+        (status = 403, description = "Missing templates:write scope", body = ErrorResponse),
+        (status = 409, description = "Another create of the same version won the race; retry", body = ErrorResponse),
+        (status = 503, description = "Database temporarily unavailable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn create_template(
     service: web::Data<TemplateService>,
+    warmer: web::Data<CacheWarmer>,
     req: web::Json<CreateTemplateRequest>,
 ) -> Result<HttpResponse, AppError> {
     let template = service.create_template(req.into_inner()).await?;
+    warmer.spawn_warm_template(template.clone());
     
     let response = ApiResponse::success(
         TemplateResponse::from(template),
@@ -37,6 +61,17 @@ pub async fn create_template(
     Ok(HttpResponse::Created().json(response))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/templates/{template_code}",
+    tag = "templates",
+    params(("template_code" = String, Path, description = "Template code"), GetTemplateQuery),
+    responses(
+        (status = 200, description = "Template found", body = TemplateApiResponse),
+        (status = 404, description = "Template not found", body = ErrorResponse),
+    ),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn get_template(
     service: web::Data<TemplateService>,
     path: web::Path<String>,
@@ -58,9 +93,23 @@ pub async fn get_template(
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/templates/{template_code}/render",
+    tag = "templates",
+    params(("template_code" = String, Path, description = "Template code"), RenderQuery),
+    request_body = RenderRequest,
+    responses(
+        (status = 200, description = "Template rendered", body = RenderedApiResponse),
+        (status = 400, description = "Render failed; `details` describes the template error", body = ErrorResponse),
+        (status = 404, description = "Template not found", body = ErrorResponse),
+    ),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn render_template(
     template_service: web::Data<TemplateService>,
     render_service: web::Data<RenderService>,
+    usage_service: web::Data<UsageService>,
     path: web::Path<String>,
     query: web::Query<RenderQuery>,
     req: web::Json<RenderRequest>,
@@ -73,17 +122,8 @@ pub async fn render_template(
         query.version,
     ).await?;
 
-    let template_type = TemplateType::from_str(&template.template_type)
-        .ok_or(AppError::InvalidTemplateType)?;
-
-    let rendered = render_service.render(
-        &template.template_code,
-        template.version,
-        &template.language,
-        &template_type,
-        &template.content,
-        &req.variables,
-    ).await?;
+    let rendered = render_service.render(&template, &req.variables).await?;
+    usage_service.record_render(&template);
 
     let response = ApiResponse::success(
         rendered,
@@ -93,16 +133,39 @@ pub async fn render_template(
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/templates/{template_code}/versions",
+    tag = "templates",
+    params(("template_code" = String, Path, description = "Template code")),
+    responses(
+        (status = 200, description = "All versions in every language, newest first, with usage", body = TemplateListApiResponse),
+    ),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn get_versions(
     service: web::Data<TemplateService>,
+    usage_service: web::Data<UsageService>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
     let template_code = path.into_inner();
     
     let templates = service.get_versions(&template_code).await?;
+    let usage: HashMap<(i32, String), UsageSummary> = usage_service
+        .usage_for(&template_code)
+        .await?
+        .iter()
+        .map(|u| ((u.version, u.language.clone()), UsageSummary::from(u)))
+        .collect();
     
     let responses: Vec<TemplateResponse> = templates.into_iter()
-        .map(TemplateResponse::from)
+        .map(|t| {
+            let summary = usage.get(&(t.version, t.language.clone())).cloned();
+            TemplateResponse {
+                usage: summary,
+                ..TemplateResponse::from(t)
+            }
+        })
         .collect();
     
     let response = ApiResponse::success(
@@ -113,6 +176,21 @@ pub async fn get_versions(
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/templates/{template_code}/{version}",
+    tag = "templates",
+    params(
+        ("template_code" = String, Path, description = "Template code"),
+        ("version" = i32, Path, description = "Version to deactivate"),
+    ),
+    responses(
+        (status = 200, description = "Version deactivated", body = EmptyApiResponse),
+        (status = 404, description = "Template not found", body = ErrorResponse),
+        (status = 403, description = "Missing templates:write scope", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
 pub async fn delete_template(
     service: web::Data<TemplateService>,
     path: web::Path<(String, i32)>,
@@ -127,6 +205,7 @@ pub async fn delete_template(
         error: None,
         message: "Template deleted successfully".to_string(),
         meta: None,
+        request_id: current_request_id(),
     };
 
     Ok(HttpResponse::Ok().json(response))
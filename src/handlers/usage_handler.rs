@@ -0,0 +1,50 @@
+use crate::error::AppError;
+use crate::models::{ApiResponse, UnusedTemplatesQuery};
+use crate::services::UsageService;
+use actix_web::{web, HttpResponse};
+
+const DEFAULT_UNUSED_DAYS: i64 = 30;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/templates/{template_code}/usage",
+    tag = "templates",
+    params(("template_code" = String, Path, description = "Template code")),
+    responses(
+        (status = 200, description = "Render counts per version and language, newest first", body = TemplateUsageListApiResponse),
+        (status = 404, description = "Template not found", body = ErrorResponse),
+    ),
+    security((), ("bearer_auth" = []), ("api_key" = []))
+)]
+pub async fn get_template_usage(
+    service: web::Data<UsageService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let template_code = path.into_inner();
+
+    let usage = service.usage_for(&template_code).await?;
+    if usage.is_empty() {
+        return Err(AppError::TemplateNotFound);
+    }
+
+    let response = ApiResponse::success(usage, "Template usage retrieved successfully");
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn list_unused_templates(
+    service: web::Data<UsageService>,
+    query: web::Query<UnusedTemplatesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let days = query.days.unwrap_or(DEFAULT_UNUSED_DAYS);
+    let days = i32::try_from(days)
+        .ok()
+        .filter(|d| *d >= 0)
+        .ok_or_else(|| AppError::InvalidContent("days must be a non-negative number".to_string()))?;
+
+    let usage = service.unused(days).await?;
+
+    let response = ApiResponse::success(usage, "Unused templates retrieved successfully");
+
+    Ok(HttpResponse::Ok().json(response))
+}
\ No newline at end of file
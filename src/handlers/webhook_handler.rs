@@ -0,0 +1,73 @@
+use crate::error::AppError;
+use crate::middleware::current_request_id;
+use crate::models::{ApiResponse, CreateWebhookRequest, CreatedWebhookResponse, WebhookSubscriptionResponse};
+use crate::services::WebhookService;
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+pub async fn create_webhook(
+    service: web::Data<WebhookService>,
+    req: web::Json<CreateWebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    let subscription = service.create_subscription(req.into_inner()).await?;
+    let secret = subscription.secret.clone();
+
+    let response = ApiResponse::success(
+        CreatedWebhookResponse {
+            subscription: WebhookSubscriptionResponse::from(subscription),
+            secret,
+        },
+        "Webhook created successfully; store the secret now, it will not be shown again"
+    );
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+pub async fn list_webhooks(
+    service: web::Data<WebhookService>,
+) -> Result<HttpResponse, AppError> {
+    let subscriptions = service.list_subscriptions().await?;
+
+    let responses: Vec<WebhookSubscriptionResponse> = subscriptions.into_iter()
+        .map(WebhookSubscriptionResponse::from)
+        .collect();
+
+    let response = ApiResponse::success(
+        responses,
+        "Webhooks retrieved successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn delete_webhook(
+    service: web::Data<WebhookService>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    service.deactivate_subscription(path.into_inner()).await?;
+
+    let response: ApiResponse<()> = ApiResponse {
+        success: true,
+        data: None,
+        error: None,
+        message: "Webhook deleted successfully".to_string(),
+        meta: None,
+        request_id: current_request_id(),
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn list_webhook_deliveries(
+    service: web::Data<WebhookService>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let deliveries = service.deliveries(path.into_inner()).await?;
+
+    let response = ApiResponse::success(
+        deliveries,
+        "Webhook deliveries retrieved successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
\ No newline at end of file
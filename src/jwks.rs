@@ -0,0 +1,102 @@
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct VerificationKey {
+    pub key: DecodingKey,
+    pub algorithm: Option<Algorithm>,
+}
+
+pub struct JwksStore {
+    source: String,
+    client: reqwest::Client,
+    keys: RwLock<HashMap<String, VerificationKey>>,
+}
+
+impl JwksStore {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("Failed to build HTTP client"),
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Fetches the key set from the configured file path or URL and replaces
+    /// the current keys. Keys without a `kid` are skipped.
+    pub async fn refresh(&self) -> Result<usize, String> {
+        let body = if self.source.starts_with("http://") || self.source.starts_with("https://") {
+            self.client
+                .get(&self.source)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("JWKS request failed: {}", e))?
+                .text()
+                .await
+                .map_err(|e| format!("JWKS read failed: {}", e))?
+        } else {
+            let path = self.source.strip_prefix("file://").unwrap_or(&self.source);
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("JWKS read failed: {}", e))?
+        };
+
+        let set: JwkSet = serde_json::from_str(&body)
+            .map_err(|e| format!("Invalid JWKS document: {}", e))?;
+
+        let mut keys = HashMap::new();
+        for jwk in &set.keys {
+            let Some(kid) = jwk.common.key_id.clone() else {
+                continue;
+            };
+            match Self::to_verification_key(jwk) {
+                Ok(key) => {
+                    keys.insert(kid, key);
+                }
+                Err(e) => tracing::warn!("Skipping JWK {}: {}", kid, e),
+            }
+        }
+
+        let count = keys.len();
+        *self.keys.write().unwrap() = keys;
+        Ok(count)
+    }
+
+    pub fn get(&self, kid: &str) -> Option<VerificationKey> {
+        self.keys.read().unwrap().get(kid).cloned()
+    }
+
+    pub fn spawn_refresh(self: Arc<Self>, interval_secs: u64) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match self.refresh().await {
+                    Ok(count) => tracing::debug!("Refreshed JWKS with {} keys", count),
+                    Err(e) => tracing::error!("JWKS refresh failed: {}", e),
+                }
+            }
+        });
+    }
+
+    fn to_verification_key(jwk: &Jwk) -> Result<VerificationKey, String> {
+        let key = DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?;
+        let algorithm = match jwk.common.key_algorithm {
+            Some(alg) => Some(
+                alg.to_string()
+                    .parse::<Algorithm>()
+                    .map_err(|e| e.to_string())?,
+            ),
+            None => None,
+        };
+        Ok(VerificationKey { key, algorithm })
+    }
+}
@@ -1,8 +1,16 @@
 pub mod cache;
+pub mod circuit_breaker;
 pub mod config;
+pub mod crypto;
 pub mod db;
 pub mod error;
 pub mod handlers;
+pub mod jwks;
+pub mod local_cache;
 pub mod middleware;
 pub mod models;
-pub mod services;
\ No newline at end of file
+pub mod openapi;
+pub mod rendering;
+pub mod services;
+pub mod singleflight;
+pub mod telemetry;
\ No newline at end of file
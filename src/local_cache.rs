@@ -0,0 +1,49 @@
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bounded in-process LRU cache whose entries also expire after a TTL.
+/// Used as an L1 in front of Redis; the TTL bounds staleness if an
+/// invalidation message is missed.
+pub struct LocalCache<V> {
+    entries: Mutex<LruCache<String, (Instant, V)>>,
+    ttl: Duration,
+}
+
+impl<V: Clone> LocalCache<V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        self.entries.lock().unwrap().put(key, (Instant::now(), value));
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().pop(key);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
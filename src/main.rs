@@ -1,21 +1,45 @@
-use actix_web::{middleware::Logger, web, App, HttpResponse, HttpServer};
+use actix_web::middleware::{Condition, Logger};
+use actix_web::{web, App, HttpResponse, HttpServer};
 use prometheus::{Encoder, TextEncoder};
 
+use std::sync::Arc;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
 mod cache;
+mod circuit_breaker;
 mod config;
+mod crypto;
 mod db;
 mod error;
 mod handlers;
+mod jwks;
+mod local_cache;
 mod middleware;
 mod models;
+mod openapi;
+mod rendering;
 mod services;
+mod singleflight;
+mod telemetry;
 
 use crate::config::Config;
 use crate::handlers::{
-    create_template, delete_template, get_template, get_versions, health, ready, render_template,
+    create_api_key, create_template, create_webhook, delete_template, delete_webhook, flush_cache,
+    get_template, get_template_usage, get_versions, health, inspect_template_cache, list_api_keys,
+    list_unused_templates, list_webhook_deliveries, list_webhooks, purge_template_cache, ready,
+    render_template, revoke_api_key,
+};
+use crate::jwks::JwksStore;
+use crate::middleware::auth::scopes;
+use crate::openapi::ApiDoc;
+use crate::middleware::{
+    Auth, AuthFailureLimit, JwtVerifier, Metrics, RateLimit, RateLimitPolicy, RequestId, Signature,
+    TraceContext,
+};
+use crate::services::{
+    ApiKeyService, CacheWarmer, OutboxDispatcher, RenderService, TemplateService, UsageService, WebhookService,
 };
-use crate::middleware::{Auth, Metrics};
-use crate::services::{RenderService, TemplateService};
 
 async fn metrics_handler() -> HttpResponse {
     let encoder = TextEncoder::new();
@@ -30,15 +54,8 @@ async fn metrics_handler() -> HttpResponse {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .json()
-        .init();
-
     let config = Config::from_env();
+    let tracer_provider = telemetry::init(&config);
 
     tracing::info!("Starting templates-service v{}", env!("CARGO_PKG_VERSION"));
     tracing::info!("Server will listen on {}", config.server_address());
@@ -49,49 +66,206 @@ async fn main() -> std::io::Result<()> {
 
     tracing::info!("Database connection pool created");
 
-    let redis_pool = cache::create_redis_pool(&config.redis_url)
+    let redis_pool = cache::create_redis_pool(&config)
         .await
-        .expect("Failed to create Redis connection");
-
-    tracing::info!("Redis connection established");
+        .expect("Invalid Redis configuration");
 
     let template_service = web::Data::new(TemplateService::new(
         db_pool.clone(),
         redis_pool.clone(),
+        &config,
     ));
+    template_service.spawn_invalidation_listener();
 
     let render_service = web::Data::new(RenderService::new(redis_pool.clone(), config.clone()));
 
+    let cache_warmer = web::Data::new(CacheWarmer::new(
+        template_service.get_ref().clone(),
+        render_service.get_ref().clone(),
+        config.cache_warmup_enabled,
+    ));
+    cache_warmer.spawn_warm_all();
+
+    let usage_service = web::Data::new(UsageService::new(
+        db_pool.clone(),
+        redis_pool.clone(),
+        config.usage_tracking_enabled,
+    ));
+    usage_service.spawn_flush(config.usage_flush_interval_secs);
+
+    let webhook_service = web::Data::new(WebhookService::new(db_pool.clone(), &config));
+    webhook_service.spawn_worker();
+
+    OutboxDispatcher::new(
+        db_pool.clone(),
+        redis_pool.clone(),
+        template_service.get_ref().clone(),
+        webhook_service.get_ref().clone(),
+        &config,
+    )
+    .spawn();
+
+    let api_key_service = Arc::new(ApiKeyService::new(db_pool.clone()));
+    let api_key_data = web::Data::from(api_key_service.clone());
+
     let db_data = web::Data::new(db_pool);
     let redis_data = web::Data::new(redis_pool);
-    let jwt_secret = config.jwt_secret.clone();
+    let config_data = web::Data::new(config.clone());
+
+    let jwks = match &config.jwks_url {
+        Some(url) => {
+            let store = Arc::new(JwksStore::new(url.clone()));
+            match store.refresh().await {
+                Ok(count) => tracing::info!("Loaded {} keys from JWKS", count),
+                Err(e) => tracing::error!("Initial JWKS load failed: {}", e),
+            }
+            store.clone().spawn_refresh(config.jwks_refresh_secs);
+            Some(store)
+        }
+        None => None,
+    };
+
+    let jwt_verifier = Arc::new(JwtVerifier::new(
+        config.jwt_secret.as_deref(),
+        jwks,
+        config.jwt_issuer.clone(),
+        config.jwt_audience.clone(),
+    ));
 
+    let secret_key = config.secret_key.clone();
+    let hmac_enabled = config.hmac_auth_enabled;
+    let hmac_tolerance_secs = config.hmac_timestamp_tolerance_secs;
+    let hmac_scopes = config.hmac_scopes.clone();
+    let rate_limit_enabled = config.rate_limit_enabled;
+    let rate_limit_policy = Arc::new(RateLimitPolicy::from_config(&config));
+    let require_read = config.auth_require_read;
+    let protect_ops = config.auth_protect_ops;
     let server_address = config.server_address();
 
     HttpServer::new(move || {
+        let auth = |scope| Auth::new(jwt_verifier.clone(), api_key_service.clone()).require_scope(scope);
+        let read_auth = || Condition::new(require_read, auth(scopes::READ));
+        let ops_auth = || Condition::new(protect_ops, auth(scopes::OPS));
+        let rate_limit = |route| {
+            Condition::new(
+                rate_limit_enabled,
+                RateLimit::new(redis_data.get_ref().clone(), rate_limit_policy.clone(), route),
+            )
+        };
+
         App::new()
-            .wrap(Logger::default())
+            .wrap(Condition::new(
+                hmac_enabled,
+                Signature::new(
+                    secret_key.clone(),
+                    redis_data.get_ref().clone(),
+                    hmac_tolerance_secs,
+                    hmac_scopes.clone(),
+                ),
+            ))
+            .wrap(Condition::new(
+                rate_limit_enabled,
+                AuthFailureLimit::new(redis_data.get_ref().clone(), rate_limit_policy.clone()),
+            ))
+            .wrap(RequestId)
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %{X-Request-Id}o %T"#))
             .wrap(Metrics)
+            .wrap(TraceContext)
             .app_data(template_service.clone())
             .app_data(render_service.clone())
+            .app_data(cache_warmer.clone())
+            .app_data(usage_service.clone())
+            .app_data(webhook_service.clone())
+            .app_data(api_key_data.clone())
             .app_data(db_data.clone())
             .app_data(redis_data.clone())
-            .route("/health", web::get().to(health))
-            .route("/ready", web::get().to(ready))
-            .route("/metrics", web::get().to(metrics_handler))
+            .app_data(config_data.clone())
+            .route("/health", web::get().to(health).wrap(ops_auth()))
+            .route("/ready", web::get().to(ready).wrap(ops_auth()))
+            .route("/metrics", web::get().to(metrics_handler).wrap(ops_auth()))
+            .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", ApiDoc::openapi()))
             .service(
                 web::scope("/api/v1/templates")
-                    .route("/", web::post().to(create_template).wrap(Auth::new(jwt_secret.clone())))
-                    .route("/{template_code}", web::get().to(get_template))
-                    .route("/{template_code}/render", web::post().to(render_template))
-                    .route("/{template_code}/versions", web::get().to(get_versions))
+                    .route(
+                        "/",
+                        web::post()
+                            .to(create_template)
+                            .wrap(rate_limit("create_template"))
+                            .wrap(auth(scopes::WRITE)),
+                    )
+                    .route(
+                        "/{template_code}",
+                        web::get()
+                            .to(get_template)
+                            .wrap(rate_limit("get_template"))
+                            .wrap(read_auth()),
+                    )
+                    .route(
+                        "/{template_code}/render",
+                        web::post()
+                            .to(render_template)
+                            .wrap(rate_limit("render_template"))
+                            .wrap(read_auth()),
+                    )
+                    .route(
+                        "/{template_code}/versions",
+                        web::get()
+                            .to(get_versions)
+                            .wrap(rate_limit("get_versions"))
+                            .wrap(read_auth()),
+                    )
+                    .route(
+                        "/{template_code}/usage",
+                        web::get()
+                            .to(get_template_usage)
+                            .wrap(rate_limit("get_template_usage"))
+                            .wrap(read_auth()),
+                    )
                     .route(
                         "/{template_code}/{version}",
-                        web::delete().to(delete_template).wrap(Auth::new(jwt_secret.clone())),
+                        web::delete()
+                            .to(delete_template)
+                            .wrap(rate_limit("delete_template"))
+                            .wrap(auth(scopes::WRITE)),
                     ),
             )
+            .service(
+                web::scope("/api/v1/admin/api-keys")
+                    .wrap(auth(scopes::ADMIN))
+                    .route("", web::post().to(create_api_key))
+                    .route("", web::get().to(list_api_keys))
+                    .route("/{id}", web::delete().to(revoke_api_key)),
+            )
+            .service(
+                web::scope("/api/v1/admin/cache")
+                    .wrap(auth(scopes::ADMIN))
+                    .route("", web::delete().to(flush_cache))
+                    .route("/templates/{template_code}", web::get().to(inspect_template_cache))
+                    .route("/templates/{template_code}", web::delete().to(purge_template_cache)),
+            )
+            .service(
+                web::scope("/api/v1/admin/usage")
+                    .wrap(auth(scopes::ADMIN))
+                    .route("/unused", web::get().to(list_unused_templates)),
+            )
+            .service(
+                web::scope("/api/v1/admin/webhooks")
+                    .wrap(auth(scopes::ADMIN))
+                    .route("", web::post().to(create_webhook))
+                    .route("", web::get().to(list_webhooks))
+                    .route("/{id}", web::delete().to(delete_webhook))
+                    .route("/{id}/deliveries", web::get().to(list_webhook_deliveries)),
+            )
     })
     .bind(&server_address)?
     .run()
-    .await
+    .await?;
+
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Failed to flush traces: {}", e);
+        }
+    }
+
+    Ok(())
 }
\ No newline at end of file
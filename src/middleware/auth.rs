@@ -1,33 +1,195 @@
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
 use actix_web::{Error, HttpMessage, HttpResponse};
 use futures::future::{ok, Ready};
 use futures::Future;
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use crate::jwks::JwksStore;
+use crate::models::ErrorResponse;
+use crate::services::ApiKeyService;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod scopes {
+    pub const READ: &str = "templates:read";
+    pub const WRITE: &str = "templates:write";
+    pub const OPS: &str = "ops";
+    pub const ADMIN: &str = "admin";
+
+    pub const ALL: [&str; 4] = [READ, WRITE, OPS, ADMIN];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
     #[serde(rename = "type")]
     pub token_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrincipalKind {
+    Jwt,
+    ApiKey,
+    Hmac,
+}
+
+impl PrincipalKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PrincipalKind::Jwt => "jwt",
+            PrincipalKind::ApiKey => "api_key",
+            PrincipalKind::Hmac => "hmac",
+        }
+    }
+}
+
+/// The authenticated caller, inserted into request extensions by `Auth`.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub kind: PrincipalKind,
+    pub scopes: Vec<String>,
+    /// Tenant the caller is confined to; `None` for callers that act across
+    /// tenants.
+    pub tenant: Option<String>,
+}
+
+impl Principal {
+    /// Access tokens without a `scope` claim keep the read/write access they
+    /// had before scopes existed; `admin` must always be granted explicitly.
+    pub fn from_claims(claims: &Claims) -> Self {
+        let scopes = match &claims.scope {
+            Some(scope) => scope.split_whitespace().map(String::from).collect(),
+            None => vec![scopes::READ.to_string(), scopes::WRITE.to_string()],
+        };
+        Self {
+            subject: claims.sub.clone(),
+            kind: PrincipalKind::Jwt,
+            scopes,
+            tenant: claims.tenant.clone(),
+        }
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope || s == scopes::ADMIN)
+    }
+}
+
+const ASYMMETRIC_ALGORITHMS: [Algorithm; 5] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+/// Verifies access tokens signed either with the shared HS256 secret or with
+/// an asymmetric key from the JWKS store selected by the token's `kid`.
+pub struct JwtVerifier {
+    secret: Option<DecodingKey>,
+    jwks: Option<Arc<JwksStore>>,
+    issuer: Option<String>,
+    audience: Vec<String>,
+}
+
+impl JwtVerifier {
+    pub fn new(
+        jwt_secret: Option<&str>,
+        jwks: Option<Arc<JwksStore>>,
+        issuer: Option<String>,
+        audience: Vec<String>,
+    ) -> Self {
+        Self {
+            secret: jwt_secret.map(|s| DecodingKey::from_secret(s.as_bytes())),
+            jwks,
+            issuer,
+            audience,
+        }
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, &'static str> {
+        let header = decode_header(token).map_err(|err| {
+            tracing::warn!("JWT header decode failed: {:?}", err);
+            "Invalid or expired token"
+        })?;
+
+        let (key, algorithm) = if header.alg == Algorithm::HS256 {
+            let key = self.secret.clone().ok_or("Unsupported token algorithm")?;
+            (key, Algorithm::HS256)
+        } else if ASYMMETRIC_ALGORITHMS.contains(&header.alg) {
+            let jwks = self.jwks.as_ref().ok_or("Unsupported token algorithm")?;
+            let kid = header.kid.as_deref().ok_or("Missing token key id")?;
+            let found = jwks.get(kid).ok_or("Unknown signing key")?;
+            if found.algorithm.is_some_and(|alg| alg != header.alg) {
+                return Err("Token algorithm does not match signing key");
+            }
+            (found.key, header.alg)
+        } else {
+            return Err("Unsupported token algorithm");
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.validate_exp = true;
+        // `set_audience`/`set_issuer` only check claims that are present, so
+        // configured ones must also be required.
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        if self.audience.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audience);
+            required.push("aud");
+        }
+        validation.set_required_spec_claims(&required);
+
+        let token_data = decode::<Claims>(token, &key, &validation).map_err(|err| {
+            tracing::warn!("JWT validation failed: {:?}", err);
+            "Invalid or expired token"
+        })?;
+
+        if token_data.claims.token_type != "access" {
+            return Err("Invalid token type");
+        }
+
+        Ok(token_data.claims)
+    }
 }
 
 pub struct Auth {
-    jwt_secret: String,
+    verifier: Arc<JwtVerifier>,
+    api_keys: Arc<ApiKeyService>,
+    required_scope: Option<&'static str>,
 }
 
 impl Auth {
-    pub fn new(jwt_secret: String) -> Self {
-        Self { jwt_secret }
+    pub fn new(verifier: Arc<JwtVerifier>, api_keys: Arc<ApiKeyService>) -> Self {
+        Self {
+            verifier,
+            api_keys,
+            required_scope: None,
+        }
+    }
+
+    pub fn require_scope(mut self, scope: &'static str) -> Self {
+        self.required_scope = Some(scope);
+        self
     }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for Auth
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -39,20 +201,24 @@ where
 
     fn new_transform(&self, service: S) -> Self::Future {
         ok(AuthMiddleware {
-            service,
-            jwt_secret: self.jwt_secret.clone(),
+            service: Rc::new(service),
+            verifier: self.verifier.clone(),
+            api_keys: self.api_keys.clone(),
+            required_scope: self.required_scope,
         })
     }
 }
 
 pub struct AuthMiddleware<S> {
-    service: S,
-    jwt_secret: String,
+    service: Rc<S>,
+    verifier: Arc<JwtVerifier>,
+    api_keys: Arc<ApiKeyService>,
+    required_scope: Option<&'static str>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -65,64 +231,67 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let auth_header = req.headers().get("Authorization");
-
-        if auth_header.is_none() {
-            return Box::pin(async {
-                let response = HttpResponse::Unauthorized().json(serde_json::json!({
-                    "success": false,
-                    "data": serde_json::Value::Null,
-                    "error": "unauthorized",
-                    "message": "Missing authorization header",
-                    "meta": serde_json::Value::Null,
-                }));
-                Err(actix_web::error::InternalError::from_response("", response).into())
-            });
-        }
+        let service = self.service.clone();
+        let verifier = self.verifier.clone();
+        let api_keys = self.api_keys.clone();
+        let required_scope = self.required_scope;
 
-        let auth_str = auth_header.unwrap().to_str().unwrap_or("");
-        let token = auth_str.strip_prefix("Bearer ").unwrap_or("");
+        Box::pin(async move {
+            let existing = req.extensions().get::<Principal>().cloned();
+            let principal = if let Some(principal) = existing {
+                principal
+            } else if let Some(key) = req.headers().get("X-API-Key") {
+                let key = key.to_str().unwrap_or("");
+                match api_keys.authenticate(key).await {
+                    Ok(Some(api_key)) => Principal {
+                        subject: api_key.id.to_string(),
+                        kind: PrincipalKind::ApiKey,
+                        scopes: api_key.scopes,
+                        tenant: api_key.tenant,
+                    },
+                    Ok(None) => return Err(reject(StatusCode::UNAUTHORIZED, "Invalid or expired API key")),
+                    Err(e) => {
+                        tracing::error!("API key lookup failed: {}", e);
+                        return Err(reject(StatusCode::UNAUTHORIZED, "Unable to verify API key"));
+                    }
+                }
+            } else if let Some(auth_header) = req.headers().get("Authorization") {
+                let auth_str = auth_header.to_str().unwrap_or("");
+                let token = auth_str.strip_prefix("Bearer ").unwrap_or("");
 
-        let jwt_secret = self.jwt_secret.clone();
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.validate_exp = true;
+                match verifier.verify(token) {
+                    Ok(claims) => {
+                        let principal = Principal::from_claims(&claims);
+                        req.extensions_mut().insert(claims);
+                        principal
+                    }
+                    Err(message) => return Err(reject(StatusCode::UNAUTHORIZED, message)),
+                }
+            } else {
+                return Err(reject(StatusCode::UNAUTHORIZED, "Missing authorization header"));
+            };
 
-        match decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(jwt_secret.as_bytes()),
-            &validation,
-        ) {
-            Ok(token_data) => {
-                if token_data.claims.token_type != "access" {
-                    return Box::pin(async {
-                        let response = HttpResponse::Unauthorized().json(serde_json::json!({
-                            "success": false,
-                            "data": serde_json::Value::Null,
-                            "error": "unauthorized",
-                            "message": "Invalid token type",
-                            "meta": serde_json::Value::Null,
-                        }));
-                        Err(actix_web::error::InternalError::from_response("", response).into())
-                    });
+            if let Some(scope) = required_scope {
+                if !principal.has_scope(scope) {
+                    return Err(reject(
+                        StatusCode::FORBIDDEN,
+                        &format!("Missing required scope: {}", scope),
+                    ));
                 }
-                
-                req.extensions_mut().insert(token_data.claims);
-                let fut = self.service.call(req);
-                Box::pin(async move { fut.await })
-            }
-            Err(err) => {
-                tracing::warn!("JWT validation failed: {:?}", err);
-                Box::pin(async {
-                    let response = HttpResponse::Unauthorized().json(serde_json::json!({
-                        "success": false,
-                        "data": serde_json::Value::Null,
-                        "error": "unauthorized",
-                        "message": "Invalid or expired token",
-                        "meta": serde_json::Value::Null,
-                    }));
-                    Err(actix_web::error::InternalError::from_response("", response).into())
-                })
             }
-        }
+
+            req.extensions_mut().insert(principal);
+            service.call(req).await
+        })
     }
-}
\ No newline at end of file
+}
+
+fn reject(status: StatusCode, message: &str) -> Error {
+    let error = if status == StatusCode::FORBIDDEN {
+        "forbidden"
+    } else {
+        "unauthorized"
+    };
+    let response = HttpResponse::build(status).json(ErrorResponse::new(error, message));
+    actix_web::error::InternalError::from_response("", response).into()
+}
@@ -1,9 +1,11 @@
+use actix_web::body::{BodySize, MessageBody};
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::Error;
 use futures::future::{ok, Ready};
 use futures::Future;
 use prometheus::{
-    register_counter_vec, register_histogram_vec, CounterVec, HistogramVec,
+    exponential_buckets, register_counter_vec, register_histogram_vec, register_int_counter,
+    register_int_gauge, CounterVec, HistogramVec, IntCounter, IntGauge,
 };
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -24,6 +26,20 @@ lazy_static::lazy_static! {
     )
     .unwrap();
 
+    pub static ref HTTP_REQUESTS_IN_FLIGHT: IntGauge = register_int_gauge!(
+        "templates_requests_in_flight",
+        "Number of HTTP requests currently being served"
+    )
+    .unwrap();
+
+    pub static ref HTTP_RESPONSE_SIZE: HistogramVec = register_histogram_vec!(
+        "templates_response_size_bytes",
+        "HTTP response body size in bytes",
+        &["method", "route"],
+        exponential_buckets(64.0, 4.0, 9).unwrap()
+    )
+    .unwrap();
+
     pub static ref TEMPLATE_CACHE_HITS: CounterVec = register_counter_vec!(
         "templates_cache_hits_total",
         "Total number of cache hits",
@@ -45,21 +61,70 @@ lazy_static::lazy_static! {
     )
     .unwrap();
 
+    pub static ref TEMPLATE_COMPILE_DURATION: HistogramVec = register_histogram_vec!(
+        "templates_compile_duration_seconds",
+        "Template compilation duration in seconds",
+        &["template_type"]
+    )
+    .unwrap();
+
+    pub static ref TEMPLATE_RENDER_ERRORS: CounterVec = register_counter_vec!(
+        "templates_render_errors_total",
+        "Total number of failed renders",
+        &["kind"]
+    )
+    .unwrap();
+
     pub static ref DB_QUERIES_TOTAL: CounterVec = register_counter_vec!(
         "templates_db_queries_total",
-        "Total number of database queries",
-        &["operation"]
+        "Total number of completed database queries",
+        &["operation", "status"]
+    )
+    .unwrap();
+
+    pub static ref COMPILED_CACHE_ENTRIES: IntGauge = register_int_gauge!(
+        "templates_compiled_cache_entries",
+        "Number of compiled templates held in memory"
+    )
+    .unwrap();
+
+    pub static ref COMPILED_CACHE_BYTES: IntGauge = register_int_gauge!(
+        "templates_compiled_cache_bytes",
+        "Approximate size of compiled templates held in memory"
+    )
+    .unwrap();
+
+    pub static ref COMPILED_CACHE_EVICTIONS: IntCounter = register_int_counter!(
+        "templates_compiled_cache_evictions_total",
+        "Total number of compiled templates evicted from memory"
+    )
+    .unwrap();
+
+    pub static ref CACHE_AVAILABLE: IntGauge = register_int_gauge!(
+        "templates_cache_available",
+        "Whether Redis is currently being used as a cache (1) or bypassed (0)"
+    )
+    .unwrap();
+
+    pub static ref OUTBOX_EVENTS_TOTAL: CounterVec = register_counter_vec!(
+        "templates_outbox_events_total",
+        "Total number of outbox dispatch attempts",
+        &["event_type", "outcome"]
     )
     .unwrap();
 }
 
 pub struct Metrics;
 
+/// Route label for requests that matched no resource, so probes for
+/// arbitrary paths share one series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
 impl<S, B> Transform<S, ServiceRequest> for Metrics
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
@@ -80,7 +145,7 @@ impl<S, B> Service<ServiceRequest> for MetricsMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
@@ -93,24 +158,64 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let start = Instant::now();
         let method = req.method().to_string();
-        let path = req.path().to_string();
+        // Resolved against the app's resource map rather than the request's
+        // match info, so it is known up front and keeps template codes and
+        // versions out of the label values.
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let in_flight = InFlightGuard::new();
 
         let fut = self.service.call(req);
 
         Box::pin(async move {
-            let res = fut.await?;
+            let _in_flight = in_flight;
+            let res = fut.await;
             let duration = start.elapsed().as_secs_f64();
-            let status = res.status().as_u16().to_string();
+
+            // Errors from inner middleware (auth, rate limiting) never
+            // become a response here, so take their status from the error.
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            }
+            .as_u16()
+            .to_string();
 
             HTTP_REQUESTS_TOTAL
-                .with_label_values(&[&method, &path, &status])
+                .with_label_values(&[&method, &route, &status])
                 .inc();
 
             HTTP_REQUEST_DURATION
-                .with_label_values(&[&method, &path])
+                .with_label_values(&[&method, &route])
                 .observe(duration);
 
-            Ok(res)
+            if let Ok(res) = &res {
+                if let BodySize::Sized(size) = res.response().body().size() {
+                    HTTP_RESPONSE_SIZE
+                        .with_label_values(&[&method, &route])
+                        .observe(size as f64);
+                }
+            }
+
+            res
         })
     }
+}
+
+/// Decrements the in-flight gauge when the request finishes, fails or is
+/// dropped by a disconnecting client.
+struct InFlightGuard;
+
+impl InFlightGuard {
+    fn new() -> Self {
+        HTTP_REQUESTS_IN_FLIGHT.inc();
+        Self
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        HTTP_REQUESTS_IN_FLIGHT.dec();
+    }
 }
\ No newline at end of file
@@ -1,5 +1,13 @@
 pub mod metrics;
 pub mod auth;
+pub mod rate_limit;
+pub mod request_id;
+pub mod signature;
+pub mod trace_context;
 
 pub use metrics::Metrics;
-pub use auth::Auth;
\ No newline at end of file
+pub use auth::{Auth, JwtVerifier};
+pub use rate_limit::{AuthFailureLimit, RateLimit, RateLimitPolicy};
+pub use request_id::{current_request_id, RequestId};
+pub use signature::Signature;
+pub use trace_context::TraceContext;
\ No newline at end of file
@@ -0,0 +1,329 @@
+use crate::cache::RedisPool;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::middleware::auth::Principal;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpMessage, ResponseError};
+use futures::future::{ok, Ready};
+use futures::Future;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Limits per window, resolved per client first, then per route, then the default.
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub window_secs: u64,
+    pub default_limit: u64,
+    pub route_limits: HashMap<String, u64>,
+    /// Keyed like [`client_key`]: `jwt:<sub>`, `api_key:<id>`,
+    /// `hmac:secret_key` or `ip:<address>`, as in `RATE_LIMIT_CLIENTS`.
+    pub client_limits: HashMap<String, u64>,
+    /// Rejected (401/403) requests allowed per client IP and window.
+    pub auth_failure_limit: u64,
+    /// Peers whose `X-Forwarded-For` is believed; empty trusts none.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimitPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            window_secs: config.rate_limit_window_secs.max(1),
+            default_limit: config.rate_limit_default,
+            route_limits: config.rate_limit_routes.clone(),
+            client_limits: config.rate_limit_clients.clone(),
+            auth_failure_limit: config.rate_limit_auth_failures,
+            trusted_proxies: config.rate_limit_trusted_proxies.clone(),
+        }
+    }
+
+    pub fn limit_for(&self, route: &str, client: &str) -> u64 {
+        self.client_limits
+            .get(client)
+            .or_else(|| self.route_limits.get(route))
+            .copied()
+            .unwrap_or(self.default_limit)
+    }
+}
+
+/// Sliding-window counter estimate: the previous window's count weighted by
+/// how much of it still overlaps the sliding window, plus the current count.
+pub fn sliding_window_estimate(previous: u64, current: u64, elapsed_secs: u64, window_secs: u64) -> u64 {
+    let remaining = window_secs.saturating_sub(elapsed_secs) as f64 / window_secs as f64;
+    (previous as f64 * remaining).floor() as u64 + current
+}
+
+pub struct RateLimit {
+    redis: RedisPool,
+    policy: Arc<RateLimitPolicy>,
+    route: &'static str,
+}
+
+impl RateLimit {
+    pub fn new(redis: RedisPool, policy: Arc<RateLimitPolicy>, route: &'static str) -> Self {
+        Self { redis, policy, route }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            redis: self.redis.clone(),
+            policy: self.policy.clone(),
+            route: self.route,
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    redis: RedisPool,
+    policy: Arc<RateLimitPolicy>,
+    route: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let mut redis = self.redis.clone();
+        let policy = self.policy.clone();
+        let route = self.route;
+        let client = client_key(&req, &policy.trusted_proxies);
+
+        Box::pin(async move {
+            let limit = policy.limit_for(route, &client);
+            let window = policy.window_secs;
+            let now = chrono::Utc::now().timestamp() as u64;
+            let window_start = now - now % window;
+            let elapsed = now - window_start;
+
+            // Hash-tagged so both windows share a cluster slot.
+            let current_key = format!("ratelimit:{{{}:{}}}:{}", route, client, window_start);
+            let previous_key = format!("ratelimit:{{{}:{}}}:{}", route, client, window_start - window);
+
+            let counts: Result<(u64, Option<u64>), redis::RedisError> = redis::pipe()
+                .atomic()
+                .incr(&current_key, 1)
+                .expire(&current_key, (window * 2) as i64)
+                .ignore()
+                .get(&previous_key)
+                .query_async(&mut redis)
+                .await;
+
+            let (current, previous) = match counts {
+                Ok(counts) => counts,
+                Err(e) => {
+                    tracing::warn!("Rate limit check failed, allowing request: {}", e);
+                    return service.call(req).await;
+                }
+            };
+
+            let used = sliding_window_estimate(previous.unwrap_or(0), current, elapsed, window);
+            let remaining = limit.saturating_sub(used);
+            let reset = window - elapsed;
+
+            if used > limit {
+                tracing::warn!("Rate limit exceeded for {} on {}", client, route);
+                let mut response = AppError::RateLimited { retry_after_secs: reset }.error_response();
+                set_headers(response.headers_mut(), limit, remaining, reset);
+                return Err(actix_web::error::InternalError::from_response("", response).into());
+            }
+
+            let mut res = service.call(req).await?;
+            set_headers(res.headers_mut(), limit, remaining, reset);
+            Ok(res)
+        })
+    }
+}
+
+/// Identifies the caller by the principal `Auth` verified, else by client
+/// IP. Unverified credentials are ignored: anyone can send a fresh one.
+/// The principal kind is part of the key because JWT subjects are chosen
+/// by the token issuer and could otherwise collide with API key ids.
+pub fn client_key(req: &ServiceRequest, trusted_proxies: &[IpAddr]) -> String {
+    if let Some(principal) = req.extensions().get::<Principal>() {
+        return format!("{}:{}", principal.kind.as_str(), principal.subject);
+    }
+    format!("ip:{}", client_ip(req, trusted_proxies))
+}
+
+/// The TCP peer, unless it is a trusted proxy: then the right-most
+/// `X-Forwarded-For` entry that is not itself a trusted proxy, since
+/// everything left of it was supplied by the client.
+pub fn client_ip(req: &ServiceRequest, trusted_proxies: &[IpAddr]) -> String {
+    let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+        return "unknown".to_string();
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>())
+        .collect::<Vec<_>>();
+    for hop in forwarded.into_iter().rev() {
+        match hop {
+            Ok(ip) if trusted_proxies.contains(&ip) => continue,
+            Ok(ip) => return ip.to_string(),
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+/// Throttles clients that keep failing authentication. Must wrap outside the
+/// route-level `Auth`, whose rejections otherwise never reach `RateLimit`.
+/// Only 401/403 responses count, per client IP.
+pub struct AuthFailureLimit {
+    redis: RedisPool,
+    policy: Arc<RateLimitPolicy>,
+}
+
+impl AuthFailureLimit {
+    pub fn new(redis: RedisPool, policy: Arc<RateLimitPolicy>) -> Self {
+        Self { redis, policy }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuthFailureLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = AuthFailureLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuthFailureLimitMiddleware {
+            service: Rc::new(service),
+            redis: self.redis.clone(),
+            policy: self.policy.clone(),
+        })
+    }
+}
+
+pub struct AuthFailureLimitMiddleware<S> {
+    service: Rc<S>,
+    redis: RedisPool,
+    policy: Arc<RateLimitPolicy>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthFailureLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let mut redis = self.redis.clone();
+        let policy = self.policy.clone();
+        let client = format!("ip:{}", client_ip(&req, &policy.trusted_proxies));
+
+        Box::pin(async move {
+            let limit = policy.auth_failure_limit;
+            let window = policy.window_secs;
+            let now = chrono::Utc::now().timestamp() as u64;
+            let window_start = now - now % window;
+            let elapsed = now - window_start;
+
+            let current_key = format!("ratelimit:{{auth_failures:{}}}:{}", client, window_start);
+            let previous_key = format!("ratelimit:{{auth_failures:{}}}:{}", client, window_start - window);
+
+            let counts: Result<(Option<u64>, Option<u64>), redis::RedisError> = redis::pipe()
+                .get(&current_key)
+                .get(&previous_key)
+                .query_async(&mut redis)
+                .await;
+            match counts {
+                Ok((current, previous)) => {
+                    let used = sliding_window_estimate(previous.unwrap_or(0), current.unwrap_or(0), elapsed, window);
+                    if used >= limit {
+                        tracing::warn!("Too many rejected requests from {}", client);
+                        let reset = window - elapsed;
+                        let mut response = AppError::RateLimited { retry_after_secs: reset }.error_response();
+                        set_headers(response.headers_mut(), limit, 0, reset);
+                        return Err(actix_web::error::InternalError::from_response("", response).into());
+                    }
+                }
+                Err(e) => tracing::warn!("Auth failure limit check failed, allowing request: {}", e),
+            }
+
+            let result = service.call(req).await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+                let recorded: Result<(), redis::RedisError> = redis::pipe()
+                    .atomic()
+                    .incr(&current_key, 1)
+                    .ignore()
+                    .expire(&current_key, (window * 2) as i64)
+                    .ignore()
+                    .query_async(&mut redis)
+                    .await;
+                if let Err(e) = recorded {
+                    tracing::warn!("Failed to record rejected request: {}", e);
+                }
+            }
+            result
+        })
+    }
+}
+
+fn set_headers(headers: &mut actix_web::http::header::HeaderMap, limit: u64, remaining: u64, reset: u64) {
+    for (name, value) in [
+        ("ratelimit-limit", limit),
+        ("ratelimit-remaining", remaining),
+        ("ratelimit-reset", reset),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
@@ -0,0 +1,106 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::error::InternalError;
+use actix_web::http::header::HeaderMap;
+use actix_web::Error;
+use futures::future::{ok, Ready};
+use futures::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The current request's id, for response envelopes built far from the
+/// `HttpRequest`. `None` outside a request, e.g. in spawned tasks.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Reuses the caller's `X-Request-Id` when it is a sane token, otherwise
+/// generates a UUID.
+pub fn resolve_request_id(header: Option<&str>) -> String {
+    header
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .filter(|id| id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Assigns every request an id, records it on the current span (the one
+/// opened by `TraceContext`, so every log line carries it) and echoes it in
+/// the `X-Request-Id` response header. Errors from inner services are
+/// rendered here, so their envelopes carry the id too.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddleware { service })
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = resolve_request_id(
+            req.headers().get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()),
+        );
+        tracing::Span::current().record("request_id", request_id.as_str());
+
+        let fut = REQUEST_ID.sync_scope(request_id.clone(), || self.service.call(req));
+
+        Box::pin(REQUEST_ID.scope(request_id.clone(), async move {
+            match fut.await {
+                Ok(mut res) => {
+                    set_header(res.headers_mut(), &request_id);
+                    Ok(res)
+                }
+                Err(e) => {
+                    // Render while the id is still in scope.
+                    let mut response = e.error_response();
+                    set_header(response.headers_mut(), &request_id);
+                    Err(InternalError::from_response(e, response).into())
+                }
+            }
+        }))
+    }
+}
+
+fn set_header(headers: &mut HeaderMap, request_id: &str) {
+    if let Ok(value) = HeaderValue::from_str(request_id) {
+        headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,189 @@
+use crate::cache::RedisPool;
+use crate::middleware::auth::{Principal, PrincipalKind};
+use crate::models::ErrorResponse;
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::Bytes;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures::future::{ok, Ready};
+use futures::Future;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+pub const NONCE_HEADER: &str = "X-Signature-Nonce";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Builds the canonical string covered by the signature:
+/// method, path with query, timestamp, nonce and hex SHA-256 of the body,
+/// separated by newlines.
+pub fn string_to_sign(method: &str, path: &str, timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    let body_hash = hex::encode(Sha256::digest(body));
+    format!("{}\n{}\n{}\n{}\n{}", method.to_uppercase(), path, timestamp, nonce, body_hash)
+}
+
+pub fn sign(secret: &str, string_to_sign: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(string_to_sign.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify(secret: &str, string_to_sign: &str, signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(string_to_sign.as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Authenticates internal callers that sign requests with `SECRET_KEY`.
+/// Requests without an `X-Signature` header pass through untouched so that
+/// route-level `Auth` can still accept JWTs and API keys.
+pub struct Signature {
+    secret: Rc<String>,
+    redis: RedisPool,
+    tolerance_secs: i64,
+    scopes: Rc<Vec<String>>,
+}
+
+impl Signature {
+    pub fn new(secret: String, redis: RedisPool, tolerance_secs: u64, scopes: Vec<String>) -> Self {
+        Self {
+            secret: Rc::new(secret),
+            redis,
+            tolerance_secs: tolerance_secs as i64,
+            scopes: Rc::new(scopes),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Signature
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SignatureMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SignatureMiddleware {
+            service: Rc::new(service),
+            secret: self.secret.clone(),
+            redis: self.redis.clone(),
+            tolerance_secs: self.tolerance_secs,
+            scopes: self.scopes.clone(),
+        })
+    }
+}
+
+pub struct SignatureMiddleware<S> {
+    service: Rc<S>,
+    secret: Rc<String>,
+    redis: RedisPool,
+    tolerance_secs: i64,
+    scopes: Rc<Vec<String>>,
+}
+
+impl<S, B> Service<ServiceRequest> for SignatureMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        let Some(signature) = header(&req, SIGNATURE_HEADER) else {
+            return Box::pin(service.call(req));
+        };
+
+        let secret = self.secret.clone();
+        let mut redis = self.redis.clone();
+        let tolerance_secs = self.tolerance_secs;
+        let scopes = self.scopes.clone();
+
+        Box::pin(async move {
+            let (Some(timestamp), Some(nonce)) = (header(&req, TIMESTAMP_HEADER), header(&req, NONCE_HEADER)) else {
+                return Err(reject("Missing signature timestamp or nonce"));
+            };
+
+            let Ok(ts) = timestamp.parse::<i64>() else {
+                return Err(reject("Invalid signature timestamp"));
+            };
+            if (chrono::Utc::now().timestamp() - ts).abs() > tolerance_secs {
+                return Err(reject("Signature timestamp outside allowed window"));
+            }
+
+            let body = req.extract::<Bytes>().await?;
+            let path = req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or_else(|| req.path())
+                .to_string();
+            let canonical = string_to_sign(req.method().as_str(), &path, &timestamp, &nonce, &body);
+
+            if !verify(&secret, &canonical, &signature) {
+                tracing::warn!("Rejected request with invalid HMAC signature");
+                return Err(reject("Invalid request signature"));
+            }
+
+            let fresh: Result<bool, redis::RedisError> = redis::cmd("SET")
+                .arg(format!("hmac_nonce:{}", nonce))
+                .arg(ts)
+                .arg("NX")
+                .arg("EX")
+                .arg(tolerance_secs * 2)
+                .query_async::<Option<String>>(&mut redis)
+                .await
+                .map(|r| r.is_some());
+            match fresh {
+                Ok(true) => {}
+                Ok(false) => return Err(reject("Request nonce already used")),
+                Err(e) => {
+                    tracing::error!("Nonce check failed: {}", e);
+                    return Err(reject("Unable to verify request signature"));
+                }
+            }
+
+            req.set_payload(Payload::from(body));
+            req.extensions_mut().insert(Principal {
+                subject: "secret_key".to_string(),
+                kind: PrincipalKind::Hmac,
+                scopes: scopes.as_ref().clone(),
+                tenant: None,
+            });
+
+            service.call(req).await
+        })
+    }
+}
+
+fn header(req: &ServiceRequest, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+fn reject(message: &str) -> Error {
+    let response = HttpResponse::Unauthorized().json(ErrorResponse::new("unauthorized", message));
+    actix_web::error::InternalError::from_response("", response).into()
+}
@@ -0,0 +1,100 @@
+use crate::middleware::metrics::UNMATCHED_ROUTE;
+use crate::telemetry::HeaderExtractor;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{ok, Ready};
+use futures::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Opens a server span per request, continuing the caller's trace when a
+/// W3C `traceparent` header is present.
+pub struct TraceContext;
+
+impl<S, B> Transform<S, ServiceRequest> for TraceContext
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = TraceContextMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TraceContextMiddleware { service })
+    }
+}
+
+pub struct TraceContextMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for TraceContextMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let method = req.method().to_string();
+        let span = tracing::info_span!(
+            "http.request",
+            otel.name = %method,
+            otel.kind = "server",
+            otel.status_code = Empty,
+            http.request.method = %method,
+            url.path = %req.path(),
+            http.route = Empty,
+            http.response.status_code = Empty,
+            request_id = Empty,
+        );
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(req.headers()))
+        });
+        span.set_parent(parent);
+
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let span = tracing::Span::current();
+                let res = fut.await;
+                match &res {
+                    Ok(res) => {
+                        // Named by route pattern, like the HTTP metrics, once
+                        // routing has run.
+                        let route = res
+                            .request()
+                            .match_pattern()
+                            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+                        span.record("otel.name", format!("{} {}", method, route));
+                        span.record("http.route", route);
+                        span.record("http.response.status_code", res.status().as_u16());
+                        if res.status().is_server_error() {
+                            span.record("otel.status_code", "ERROR");
+                        }
+                    }
+                    Err(_) => {
+                        span.record("otel.status_code", "ERROR");
+                    }
+                }
+                res
+            }
+            .instrument(span),
+        )
+    }
+}
\ No newline at end of file
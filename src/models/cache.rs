@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct CacheKeyInfo {
+    pub key: String,
+    pub ttl_secs: Option<i64>,
+}
+
+/// `compiled` describes the replica that served the request.
+#[derive(Debug, Serialize)]
+pub struct TemplateCacheState {
+    pub version: i32,
+    pub language: String,
+    pub is_active: bool,
+    pub template_key: Option<CacheKeyInfo>,
+    pub rendered_keys: Vec<CacheKeyInfo>,
+    pub compiled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheInspectionResponse {
+    pub template_code: String,
+    pub latest_keys: Vec<CacheKeyInfo>,
+    pub versions: Vec<TemplateCacheState>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CachePurgeResponse {
+    pub purged_versions: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheFlushResponse {
+    /// Redis keys deleted; other replicas are told to drop them too.
+    pub template_keys: usize,
+    pub rendered_keys: usize,
+    /// Compiled templates dropped by the replica that served the request.
+    /// Other replicas keep theirs: entries are keyed by content hash, so
+    /// they can never be stale and only cost memory until evicted.
+    pub compiled_templates: usize,
+}
\ No newline at end of file
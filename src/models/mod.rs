@@ -1,5 +1,15 @@
+pub mod api_key;
+pub mod cache;
+pub mod outbox;
 pub mod template;
 pub mod response;
+pub mod usage;
+pub mod webhook;
 
+pub use api_key::*;
+pub use cache::*;
+pub use outbox::*;
 pub use template::*;
-pub use response::*;
\ No newline at end of file
+pub use response::*;
+pub use usage::*;
+pub use webhook::*;
\ No newline at end of file
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::WebhookEvent;
+
+/// A change event written in the same transaction as the change itself.
+/// `id` is the idempotency key: it is the event id seen by stream
+/// consumers and webhook receivers, however many times it is dispatched.
+#[derive(Debug, Clone, FromRow)]
+pub struct OutboxEvent {
+    pub id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    pub occurred_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+impl From<OutboxEvent> for WebhookEvent {
+    fn from(e: OutboxEvent) -> Self {
+        Self {
+            id: e.id,
+            event_type: e.event_type,
+            occurred_at: e.occurred_at,
+            data: e.payload,
+        }
+    }
+}
\ No newline at end of file
@@ -1,6 +1,16 @@
+use crate::middleware::current_request_id;
+use crate::models::{TemplateResponse, TemplateUsage};
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    TemplateApiResponse = ApiResponse<TemplateResponse>,
+    TemplateListApiResponse = ApiResponse<Vec<TemplateResponse>>,
+    RenderedApiResponse = ApiResponse<RenderedTemplate>,
+    TemplateUsageListApiResponse = ApiResponse<Vec<TemplateUsage>>,
+    EmptyApiResponse = ApiResponse<EmptyData>,
+)]
 pub struct ApiResponse<T> {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -10,9 +20,12 @@ pub struct ApiResponse<T> {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<PaginationMeta>,
+    /// Echo of the `X-Request-Id` response header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PaginationMeta {
     pub total: i64,
     pub has_previous: bool,
@@ -26,6 +39,111 @@ impl<T> ApiResponse<T> {
             error: None,
             message: message.into(),
             meta: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    #[schema(example = false)]
+    pub success: bool,
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<serde_json::Value>,
+    #[schema(example = "template_not_found")]
+    pub error: String,
+    #[schema(example = "Template not found")]
+    pub message: String,
+    #[schema(value_type = Option<Object>)]
+    pub meta: Option<serde_json::Value>,
+    /// Set for template syntax and render errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<TemplateErrorDetails>,
+    /// Quote this when reporting a problem; it matches the service logs.
+    #[schema(example = "0f6b1c1e-6b5e-4a8e-9a55-2d8f1d1f3c7a")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: error.into(),
+            message: message.into(),
+            meta: None,
+            details: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+/// Stage at which a template failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateErrorKind {
+    /// The template has a syntax error.
+    Compile,
+    /// The variables could not be turned into a render context.
+    Context,
+    /// Tera failed while rendering, e.g. on a missing variable.
+    Render,
+    /// The rendered output is not valid for the template type.
+    InvalidOutput,
+}
+
+impl TemplateErrorKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TemplateErrorKind::Compile => "compile",
+            TemplateErrorKind::Context => "context",
+            TemplateErrorKind::Render => "render",
+            TemplateErrorKind::InvalidOutput => "invalid_output",
+        }
+    }
+}
+
+/// Why a template failed to compile or render. `line` and `column` are
+/// 1-based and, with `snippet` (the offending source line), only present
+/// for syntax errors. `causes` lists the underlying errors, outermost first.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TemplateErrorDetails {
+    pub kind: TemplateErrorKind,
+    #[schema(example = "expected an identifier (must start with a-z)")]
+    pub message: String,
+    #[schema(example = 1)]
+    pub line: Option<usize>,
+    #[schema(example = 8)]
+    pub column: Option<usize>,
+    #[schema(example = "{% for %}")]
+    pub snippet: Option<String>,
+    pub causes: Vec<String>,
+}
+
+impl TemplateErrorDetails {
+    pub fn new(kind: TemplateErrorKind, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            kind,
+            causes: vec![message.clone()],
+            message,
+            line: None,
+            column: None,
+            snippet: None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// `data` of a render response: an HTML string for `email_html`
+/// templates, a JSON object with `title` and `body` for `push_json`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RenderedTemplate {
+    #[schema(value_type = Object)]
+    pub rendered: serde_json::Value,
+}
+
+/// Placeholder for responses whose `data` is always omitted.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmptyData {}
\ No newline at end of file
@@ -1,8 +1,13 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::str::FromStr;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::UsageSummary;
+use crate::error::AppError;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum TemplateType {
@@ -17,12 +22,16 @@ impl TemplateType {
             TemplateType::PushJson => "push_json",
         }
     }
+}
+
+impl FromStr for TemplateType {
+    type Err = AppError;
 
-    pub fn from_str(s: &str) -> Option<Self> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "email_html" => Some(TemplateType::EmailHtml),
-            "push_json" => Some(TemplateType::PushJson),
-            _ => None,
+            "email_html" => Ok(TemplateType::EmailHtml),
+            "push_json" => Ok(TemplateType::PushJson),
+            _ => Err(AppError::InvalidTemplateType),
         }
     }
 }
@@ -44,22 +53,29 @@ pub struct Template {
     pub meta: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateTemplateRequest {
+    #[schema(example = "welcome_email")]
     pub template_code: String,
     #[serde(rename = "type")]
+    #[schema(example = "email_html")]
     pub template_type: String,
+    #[schema(example = "en")]
     pub language: String,
+    #[schema(example = "<p>Hello {{ name }}</p>")]
     pub content: String,
+    /// Free-form metadata. `sensitive` and `cache` control render caching.
+    #[schema(value_type = Option<Object>)]
     pub meta: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct TemplateResponse {
     pub id: Uuid,
     pub template_code: String,
     pub version: i32,
     #[serde(rename = "type")]
+    #[schema(example = "email_html")]
     pub template_type: String,
     pub language: String,
     pub content: String,
@@ -67,7 +83,12 @@ pub struct TemplateResponse {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub is_active: bool,
+    #[schema(value_type = Option<Object>)]
     pub meta: Option<serde_json::Value>,
+    /// Render statistics, included in listings. Renders since the last
+    /// periodic flush are not yet counted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageSummary>,
 }
 
 impl From<Template> for TemplateResponse {
@@ -84,6 +105,7 @@ impl From<Template> for TemplateResponse {
             updated_at: t.updated_at,
             is_active: t.is_active,
             meta: t.meta,
+            usage: None,
         }
     }
 }
\ No newline at end of file
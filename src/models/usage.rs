@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct TemplateUsage {
+    pub template_code: String,
+    pub version: i32,
+    pub language: String,
+    pub render_count: i64,
+    pub last_rendered_at: Option<DateTime<Utc>>,
+}
+
+/// Usage attached to a template in listings.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageSummary {
+    pub render_count: i64,
+    pub last_rendered_at: Option<DateTime<Utc>>,
+}
+
+impl From<&TemplateUsage> for UsageSummary {
+    fn from(u: &TemplateUsage) -> Self {
+        Self {
+            render_count: u.render_count,
+            last_rendered_at: u.last_rendered_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UnusedTemplatesQuery {
+    /// Active versions not rendered for this many days (or never) are
+    /// returned. Defaults to 30.
+    pub days: Option<i64>,
+}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::Template;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookSubscriptionResponse {
+    pub id: Uuid,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookSubscription> for WebhookSubscriptionResponse {
+    fn from(s: WebhookSubscription) -> Self {
+        Self {
+            id: s.id,
+            url: s.url,
+            event_types: s.event_types,
+            description: s.description,
+            is_active: s.is_active,
+            created_at: s.created_at,
+            deactivated_at: s.deactivated_at,
+        }
+    }
+}
+
+/// Returned only from the create endpoint; `secret` verifies the
+/// `X-Webhook-Signature` header and is never shown again.
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhookResponse {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscriptionResponse,
+    pub secret: String,
+}
+
+/// One attempt log entry per (subscription, event).
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    /// `pending`, `succeeded`, `failed` (attempts exhausted) or `cancelled`
+    /// (subscription removed first).
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A template change event: the body POSTed to webhook subscribers and the
+/// entry added to the change stream. `id` is stable across retries so
+/// consumers can deduplicate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+pub mod webhook_events {
+    pub const TEMPLATE_CREATED: &str = "template.created";
+    pub const TEMPLATE_DELETED: &str = "template.deleted";
+
+    pub const ALL: [&str; 2] = [TEMPLATE_CREATED, TEMPLATE_DELETED];
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: &str, data: Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type: event_type.to_string(),
+            occurred_at: Utc::now(),
+            data,
+        }
+    }
+
+    pub fn template_created(template: &Template) -> Self {
+        Self::new(
+            webhook_events::TEMPLATE_CREATED,
+            serde_json::json!({
+                "template_code": template.template_code,
+                "version": template.version,
+                "language": template.language,
+                "template_type": template.template_type,
+            }),
+        )
+    }
+
+    /// A version was deactivated in every language.
+    pub fn template_deleted(template_code: &str, version: i32, languages: &[String]) -> Self {
+        Self::new(
+            webhook_events::TEMPLATE_DELETED,
+            serde_json::json!({
+                "template_code": template_code,
+                "version": version,
+                "languages": languages,
+            }),
+        )
+    }
+}
\ No newline at end of file
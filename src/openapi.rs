@@ -0,0 +1,72 @@
+use crate::handlers::{health_handler, template_handler, usage_handler};
+use crate::models::{
+    CreateTemplateRequest, EmptyApiResponse, EmptyData, ErrorResponse, PaginationMeta, RenderedApiResponse,
+    RenderedTemplate, TemplateApiResponse, TemplateListApiResponse, TemplateResponse, TemplateUsage,
+    TemplateErrorDetails, TemplateErrorKind, TemplateUsageListApiResponse, UsageSummary,
+};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// OpenAPI 3 description of the public API, served at
+/// `/api-docs/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "templates-service", description = "Versioned notification templates and rendering"),
+    paths(
+        template_handler::create_template,
+        template_handler::get_template,
+        template_handler::render_template,
+        template_handler::get_versions,
+        template_handler::delete_template,
+        usage_handler::get_template_usage,
+        health_handler::health,
+        health_handler::ready,
+    ),
+    components(schemas(
+        CreateTemplateRequest,
+        TemplateResponse,
+        UsageSummary,
+        TemplateUsage,
+        template_handler::RenderRequest,
+        RenderedTemplate,
+        EmptyData,
+        PaginationMeta,
+        ErrorResponse,
+        TemplateErrorDetails,
+        TemplateErrorKind,
+        TemplateApiResponse,
+        TemplateListApiResponse,
+        RenderedApiResponse,
+        TemplateUsageListApiResponse,
+        EmptyApiResponse,
+        health_handler::HealthResponse,
+        health_handler::ReadyResponse,
+    )),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "templates", description = "Template management and rendering"),
+        (name = "operations", description = "Health and readiness probes"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::models::{TemplateErrorDetails, TemplateErrorKind};
+use tera::Tera;
+
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// Compiles `content` as a Tera template registered under `name`.
+pub fn compile(name: &str, content: &str) -> Result<Tera, Box<TemplateErrorDetails>> {
+    let mut tera = Tera::default();
+    tera.add_raw_template(name, content)
+        .map_err(|e| Box::new(describe_tera_error(TemplateErrorKind::Compile, &e, name, content)))?;
+    Ok(tera)
+}
+
+/// Flattens a Tera error chain into [`TemplateErrorDetails`]. Tera reports
+/// syntax errors as a pest message (` --> line:col`, the source line, then
+/// `= expected ...`); the location and expectation are pulled out of it.
+/// `name`, an internal cache key, is dropped from the messages.
+pub fn describe_tera_error(kind: TemplateErrorKind, err: &tera::Error, name: &str, content: &str) -> TemplateErrorDetails {
+    let quoted_name = format!(" '{}'", name);
+    let mut causes = Vec::new();
+    let mut location = None;
+
+    let mut current: Option<&dyn std::error::Error> = Some(err);
+    while let Some(e) = current {
+        let text = e.to_string();
+        if let Some(found) = parse_location(&text) {
+            location = Some(found);
+            let expected: Vec<&str> = text
+                .lines()
+                .filter_map(|line| line.trim_start().strip_prefix("= "))
+                .collect();
+            if !expected.is_empty() {
+                causes.push(expected.join("; "));
+            }
+        } else {
+            causes.push(text.replace(&quoted_name, ""));
+        }
+        current = e.source();
+    }
+
+    let snippet = location.and_then(|(line, _)| {
+        content
+            .lines()
+            .nth(line.checked_sub(1)?)
+            .map(|source| source.chars().take(MAX_SNIPPET_CHARS).collect())
+    });
+
+    TemplateErrorDetails {
+        kind,
+        message: causes.last().cloned().unwrap_or_else(|| err.to_string()),
+        line: location.map(|(line, _)| line),
+        column: location.map(|(_, column)| column),
+        snippet,
+        causes,
+    }
+}
+
+fn parse_location(text: &str) -> Option<(usize, usize)> {
+    let rest = &text[text.find("--> ")? + 4..];
+    let position = rest.lines().next()?.trim();
+    let (line, column) = position.split_once(':')?;
+    Some((line.parse().ok()?, column.parse().ok()?))
+}
\ No newline at end of file
@@ -0,0 +1,154 @@
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::middleware::auth::scopes;
+use crate::models::{ApiKey, CreateApiKeyRequest};
+use crate::telemetry::DbQuery;
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const KEY_PREFIX: &str = "tsk_";
+const KEY_BYTES: usize = 32;
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+pub struct ApiKeyService {
+    pool: DbPool,
+}
+
+impl ApiKeyService {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates a key and returns it together with its plaintext, which is not
+    /// stored and cannot be recovered afterwards. A caller bound to
+    /// `caller_tenant` can only create keys for that tenant.
+    pub async fn create_key(
+        &self,
+        req: CreateApiKeyRequest,
+        caller_tenant: Option<&str>,
+    ) -> Result<(ApiKey, String), AppError> {
+        if req.name.trim().is_empty() {
+            return Err(AppError::InvalidContent("API key name cannot be empty".to_string()));
+        }
+        if req.scopes.is_empty() {
+            return Err(AppError::InvalidScope("At least one scope is required".to_string()));
+        }
+        if let Some(scope) = req.scopes.iter().find(|s| !scopes::ALL.contains(&s.as_str())) {
+            return Err(AppError::InvalidScope(format!("Unknown scope: {}", scope)));
+        }
+        let tenant = match (caller_tenant, req.tenant) {
+            (Some(caller), Some(requested)) if caller != requested => {
+                return Err(AppError::Forbidden("Cannot create API keys for another tenant".to_string()));
+            }
+            (Some(caller), _) => Some(caller.to_string()),
+            (None, requested) => requested,
+        };
+
+        let plaintext = generate_key();
+
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            r#"
+            INSERT INTO api_keys (name, key_prefix, key_hash, scopes, tenant, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#
+        )
+        .bind(req.name.trim())
+        .bind(&plaintext[..KEY_PREFIX.len() + 8])
+        .bind(hash_key(&plaintext))
+        .bind(&req.scopes)
+        .bind(&tenant)
+        .bind(req.expires_at)
+        .fetch_one(&self.pool)
+        .db_query("insert_api_key")
+        .await?;
+
+        Ok((api_key, plaintext))
+    }
+
+    /// Every key, or only `tenant`'s keys when the caller is bound to one.
+    pub async fn list_keys(&self, tenant: Option<&str>) -> Result<Vec<ApiKey>, AppError> {
+        let keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE $1::text IS NULL OR tenant = $1 ORDER BY created_at DESC"
+        )
+        .bind(tenant)
+        .fetch_all(&self.pool)
+        .db_query("select_api_keys")
+        .await?;
+
+        Ok(keys)
+    }
+
+    /// Keys of other tenants are reported as not found to a tenant-bound
+    /// caller, so their ids cannot be probed.
+    pub async fn revoke_key(&self, id: Uuid, tenant: Option<&str>) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_keys SET revoked_at = now()
+            WHERE id = $1 AND revoked_at IS NULL AND ($2::text IS NULL OR tenant = $2)
+            "#
+        )
+        .bind(id)
+        .bind(tenant)
+        .execute(&self.pool)
+        .db_query("revoke_api_key")
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::ApiKeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a presented key by hash and returns it if it is neither revoked
+    /// nor expired. `last_used_at` is refreshed at most once a minute.
+    pub async fn authenticate(&self, plaintext: &str) -> Result<Option<ApiKey>, AppError> {
+        if !plaintext.starts_with(KEY_PREFIX) {
+            return Ok(None);
+        }
+
+        let api_key = sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys WHERE key_hash = $1")
+            .bind(hash_key(plaintext))
+            .fetch_optional(&self.pool)
+            .db_query("select_api_key_by_hash")
+            .await?;
+
+        let now = Utc::now();
+        let Some(api_key) = api_key.filter(|k| k.is_usable(now)) else {
+            return Ok(None);
+        };
+
+        if last_used_is_stale(api_key.last_used_at, now) {
+            sqlx::query("UPDATE api_keys SET last_used_at = now() WHERE id = $1")
+                .bind(api_key.id)
+                .execute(&self.pool)
+                .db_query("update_api_key_last_used")
+                .await?;
+        }
+
+        Ok(Some(api_key))
+    }
+}
+
+/// A new plaintext key: the prefix followed by 32 random bytes in hex.
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; KEY_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", KEY_PREFIX, hex::encode(bytes))
+}
+
+pub fn hash_key(plaintext: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(plaintext.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Whether a use at `now` should be written back, so a busy key costs at
+/// most one write per minute.
+pub fn last_used_is_stale(last_used_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    last_used_at.is_none_or(|t| now - t > Duration::seconds(LAST_USED_RESOLUTION_SECS))
+}
\ No newline at end of file
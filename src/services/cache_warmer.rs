@@ -0,0 +1,84 @@
+use crate::error::AppError;
+use crate::models::Template;
+use crate::services::{RenderService, TemplateService};
+
+/// Populates Redis and the compiled-template cache ahead of traffic, so the
+/// first requests after a deploy, Redis flush or publish skip Postgres and
+/// template compilation. Does nothing unless enabled.
+#[derive(Clone)]
+pub struct CacheWarmer {
+    templates: TemplateService,
+    renderer: RenderService,
+    enabled: bool,
+}
+
+impl CacheWarmer {
+    pub fn new(templates: TemplateService, renderer: RenderService, enabled: bool) -> Self {
+        Self {
+            templates,
+            renderer,
+            enabled,
+        }
+    }
+
+    /// Warms the latest active version of every template; returns how many
+    /// were warmed. Templates that fail to compile are skipped.
+    pub async fn warm_all(&self) -> Result<usize, AppError> {
+        let templates = self.templates.latest_active().await?;
+        let mut warmed = 0;
+        for template in &templates {
+            if self.warm_template(template).await {
+                warmed += 1;
+            }
+        }
+        Ok(warmed)
+    }
+
+    async fn warm_template(&self, template: &Template) -> bool {
+        if let Err(e) = self.templates.warm(template).await {
+            tracing::warn!(
+                "Could not warm the latest {} ({}): {}",
+                template.template_code,
+                template.language,
+                e
+            );
+        }
+        match self.renderer.precompile(template) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping warm-up of {} v{} ({}): {}",
+                    template.template_code,
+                    template.version,
+                    template.language,
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    pub fn spawn_warm_all(&self) {
+        if !self.enabled {
+            return;
+        }
+        let warmer = self.clone();
+        tokio::spawn(async move {
+            match warmer.warm_all().await {
+                Ok(count) => tracing::info!("Cache warm-up loaded {} templates", count),
+                Err(e) => tracing::warn!("Cache warm-up failed: {}", e),
+            }
+        });
+    }
+
+    /// Warms a newly created version in the background.
+    pub fn spawn_warm_template(&self, template: Template) {
+        if !self.enabled {
+            return;
+        }
+        let warmer = self.clone();
+        tokio::spawn(async move {
+            warmer.warm_template(&template).await;
+        });
+    }
+}
@@ -0,0 +1,98 @@
+use crate::middleware::metrics::{COMPILED_CACHE_BYTES, COMPILED_CACHE_ENTRIES, COMPILED_CACHE_EVICTIONS};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use tera::Tera;
+
+struct Entry {
+    tera: Arc<Tera>,
+    size: usize,
+}
+
+struct Entries {
+    lru: LruCache<String, Entry>,
+    bytes: usize,
+}
+
+impl Entries {
+    fn update_gauges(&self) {
+        COMPILED_CACHE_ENTRIES.set(self.lru.len() as i64);
+        COMPILED_CACHE_BYTES.set(self.bytes as i64);
+    }
+}
+
+/// Compiled Tera templates keyed by content hash, bounded by entry count and
+/// approximate size. Every access takes the one lock, since a lookup also
+/// bumps the entry's recency, but it is only held to clone or insert an
+/// `Arc`: renders run outside it and share the compiled template. Inserts
+/// evict least-recently-used entries until within bounds.
+pub struct CompiledCache {
+    entries: Mutex<Entries>,
+    max_bytes: usize,
+}
+
+impl CompiledCache {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(Entries {
+                lru: LruCache::new(capacity),
+                bytes: 0,
+            }),
+            max_bytes,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<Tera>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.lru.get(key).map(|entry| entry.tera.clone())
+    }
+
+    /// `size` is the caller's estimate of the entry's footprint; the source
+    /// length is a reasonable proxy for the parsed AST.
+    pub fn insert(&self, key: String, tera: Arc<Tera>, size: usize) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.bytes += size;
+        if let Some((displaced_key, displaced)) = entries.lru.push(key.clone(), Entry { tera, size }) {
+            entries.bytes -= displaced.size;
+            if displaced_key != key {
+                COMPILED_CACHE_EVICTIONS.inc();
+            }
+        }
+
+        while entries.bytes > self.max_bytes && entries.lru.len() > 1 {
+            let Some((_, evicted)) = entries.lru.pop_lru() else {
+                break;
+            };
+            entries.bytes -= evicted.size;
+            COMPILED_CACHE_EVICTIONS.inc();
+        }
+
+        entries.update_gauges();
+    }
+
+    /// Presence check that does not count as a use.
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().lru.contains(key)
+    }
+
+    pub fn remove(&self, key: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(removed) = entries.lru.pop(key) else {
+            return false;
+        };
+        entries.bytes -= removed.size;
+        entries.update_gauges();
+        true
+    }
+
+    /// Drops every entry and returns how many there were.
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.lru.len();
+        entries.lru.clear();
+        entries.bytes = 0;
+        entries.update_gauges();
+        count
+    }
+}
\ No newline at end of file
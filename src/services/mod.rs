@@ -1,5 +1,17 @@
+pub mod api_key_service;
+pub mod cache_warmer;
+pub mod compiled_cache;
+pub mod outbox_dispatcher;
 pub mod template_service;
 pub mod render_service;
+pub mod usage_service;
+pub mod webhook_service;
 
+pub use api_key_service::ApiKeyService;
+pub use cache_warmer::CacheWarmer;
+pub use compiled_cache::CompiledCache;
+pub use outbox_dispatcher::OutboxDispatcher;
 pub use template_service::TemplateService;
-pub use render_service::RenderService;
\ No newline at end of file
+pub use render_service::RenderService;
+pub use usage_service::UsageService;
+pub use webhook_service::WebhookService;
\ No newline at end of file
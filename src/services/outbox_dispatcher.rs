@@ -0,0 +1,230 @@
+use crate::cache::RedisPool;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::middleware::metrics::OUTBOX_EVENTS_TOTAL;
+use crate::models::{webhook_events, OutboxEvent, WebhookEvent};
+use crate::services::webhook_service::backoff_secs;
+use crate::services::{TemplateService, WebhookService};
+use crate::telemetry::DbQuery;
+use sqlx::PgConnection;
+use std::time::Duration;
+
+/// Claimed events are skipped by other dispatchers for this long; a
+/// dispatcher that dies mid-batch only delays them.
+const CLAIM_LEASE_SECS: f64 = 60.0;
+const RETRY_BASE_SECS: u64 = 1;
+const RETRY_MAX_SECS: u64 = 300;
+const MAX_ERROR_LEN: usize = 500;
+
+/// Writes `event` to the outbox on `conn`, which should be the transaction
+/// making the change it describes.
+pub async fn record(conn: &mut PgConnection, event: &WebhookEvent) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO outbox_events (id, event_type, payload, occurred_at)
+        VALUES ($1, $2, $3, $4)
+        "#
+    )
+    .bind(event.id)
+    .bind(&event.event_type)
+    .bind(&event.data)
+    .bind(event.occurred_at)
+    .execute(conn)
+    .db_query("insert_outbox_event")
+    .await?;
+
+    Ok(())
+}
+
+/// Cache entries to drop for an event, as (template code, version, language).
+pub fn invalidation_targets(event: &WebhookEvent) -> Vec<(String, i32, String)> {
+    let data = &event.data;
+    let (Some(code), Some(version)) = (
+        data["template_code"].as_str(),
+        data["version"].as_i64().and_then(|v| i32::try_from(v).ok()),
+    ) else {
+        return Vec::new();
+    };
+
+    let languages: Vec<&str> = match event.event_type.as_str() {
+        webhook_events::TEMPLATE_CREATED => data["language"].as_str().into_iter().collect(),
+        webhook_events::TEMPLATE_DELETED => data["languages"]
+            .as_array()
+            .map(|langs| langs.iter().filter_map(|l| l.as_str()).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    languages
+        .into_iter()
+        .map(|lang| (code.to_string(), version, lang.to_string()))
+        .collect()
+}
+
+/// Field/value pairs of the stream entry for an event.
+pub fn stream_fields(event: &WebhookEvent) -> Vec<(&'static str, String)> {
+    vec![
+        ("event_id", event.id.to_string()),
+        ("type", event.event_type.clone()),
+        ("occurred_at", event.occurred_at.to_rfc3339()),
+        ("data", event.data.to_string()),
+    ]
+}
+
+/// Publishes outbox events: invalidates the affected cache entries, queues
+/// webhooks and appends the event to a Redis stream, then marks it
+/// dispatched. Any step failing retries the whole event, so delivery is
+/// at least once; consumers deduplicate on `event_id`.
+#[derive(Clone)]
+pub struct OutboxDispatcher {
+    pool: DbPool,
+    redis: RedisPool,
+    templates: TemplateService,
+    webhooks: WebhookService,
+    enabled: bool,
+    poll_interval_secs: u64,
+    batch_size: i64,
+    stream_key: String,
+    stream_maxlen: u64,
+    retention_hours: i32,
+}
+
+impl OutboxDispatcher {
+    pub fn new(
+        pool: DbPool,
+        redis: RedisPool,
+        templates: TemplateService,
+        webhooks: WebhookService,
+        config: &Config,
+    ) -> Self {
+        Self {
+            pool,
+            redis,
+            templates,
+            webhooks,
+            enabled: config.outbox_dispatcher_enabled,
+            poll_interval_secs: config.outbox_poll_interval_secs,
+            batch_size: config.outbox_batch_size,
+            stream_key: config.outbox_stream_key.clone(),
+            stream_maxlen: config.outbox_stream_maxlen,
+            retention_hours: config.outbox_retention_hours,
+        }
+    }
+
+    /// Claims and publishes one batch of due events, oldest first. Returns
+    /// how many were claimed.
+    pub async fn dispatch_pending(&self) -> Result<usize, AppError> {
+        let mut events = sqlx::query_as::<_, OutboxEvent>(
+            r#"
+            UPDATE outbox_events
+            SET attempts = attempts + 1,
+                next_attempt_at = now() + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM outbox_events
+                WHERE dispatched_at IS NULL AND next_attempt_at <= now()
+                ORDER BY occurred_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, event_type, payload, occurred_at, attempts
+            "#
+        )
+        .bind(self.batch_size)
+        .bind(CLAIM_LEASE_SECS)
+        .fetch_all(&self.pool)
+        .db_query("claim_outbox_events")
+        .await?;
+        events.sort_by_key(|e| e.occurred_at);
+
+        let count = events.len();
+        for event in events {
+            let attempts = event.attempts;
+            let event = WebhookEvent::from(event);
+            match self.publish(&event).await {
+                Ok(()) => {
+                    OUTBOX_EVENTS_TOTAL.with_label_values(&[&event.event_type, "dispatched"]).inc();
+                    sqlx::query("UPDATE outbox_events SET dispatched_at = now(), last_error = NULL WHERE id = $1")
+                        .bind(event.id)
+                        .execute(&self.pool)
+                        .db_query("mark_outbox_dispatched")
+                        .await?;
+                }
+                Err(e) => {
+                    OUTBOX_EVENTS_TOTAL.with_label_values(&[&event.event_type, "failed"]).inc();
+                    tracing::warn!("Failed to dispatch {} event {} (attempt {}): {}", event.event_type, event.id, attempts, e);
+                    let retry_in = backoff_secs(attempts, RETRY_BASE_SECS, RETRY_MAX_SECS) as f64;
+                    let error: String = e.to_string().chars().take(MAX_ERROR_LEN).collect();
+                    sqlx::query(
+                        "UPDATE outbox_events SET next_attempt_at = now() + make_interval(secs => $2), last_error = $3 WHERE id = $1"
+                    )
+                    .bind(event.id)
+                    .bind(retry_in)
+                    .bind(error)
+                    .execute(&self.pool)
+                    .db_query("mark_outbox_failed")
+                    .await?;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn publish(&self, event: &WebhookEvent) -> Result<(), AppError> {
+        for (template_code, version, language) in invalidation_targets(event) {
+            self.templates.invalidate_template_cache(&template_code, version, &language).await?;
+        }
+
+        self.webhooks.enqueue(event).await?;
+
+        let mut cmd = redis::cmd("XADD");
+        cmd.arg(&self.stream_key).arg("MAXLEN").arg("~").arg(self.stream_maxlen).arg("*");
+        for (field, value) in stream_fields(event) {
+            cmd.arg(field).arg(value);
+        }
+        let mut redis_conn = self.redis.clone();
+        cmd.query_async::<String>(&mut redis_conn).await?;
+
+        Ok(())
+    }
+
+    /// Deletes dispatched events past the retention period.
+    pub async fn prune(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            "DELETE FROM outbox_events WHERE dispatched_at < now() - make_interval(hours => $1)"
+        )
+        .bind(self.retention_hours)
+        .execute(&self.pool)
+        .db_query("prune_outbox_events")
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub fn spawn(&self) {
+        if !self.enabled {
+            return;
+        }
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(dispatcher.poll_interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                loop {
+                    match dispatcher.dispatch_pending().await {
+                        Ok(count) if count as i64 >= dispatcher.batch_size => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::warn!("Outbox dispatch failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                if let Err(e) = dispatcher.prune().await {
+                    tracing::warn!("Failed to prune outbox: {}", e);
+                }
+            }
+        });
+    }
+}
\ No newline at end of file
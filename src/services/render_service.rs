@@ -1,120 +1,278 @@
-use crate::cache::RedisPool;
+use crate::cache::{self, CachePolicy, Cached, RedisPool};
 use crate::config::Config;
+use crate::crypto::PayloadCipher;
 use crate::error::AppError;
-use crate::models::TemplateType;
+use crate::middleware::metrics::{
+    TEMPLATE_CACHE_HITS, TEMPLATE_CACHE_MISSES, TEMPLATE_COMPILE_DURATION, TEMPLATE_RENDER_DURATION,
+    TEMPLATE_RENDER_ERRORS,
+};
+use crate::models::{CacheKeyInfo, Template, TemplateErrorDetails, TemplateErrorKind, TemplateType};
+use crate::rendering;
+use crate::services::CompiledCache;
+use crate::singleflight::SingleFlight;
 use redis::AsyncCommands;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tera::Tera;
-use tokio::sync::RwLock;
 
+#[derive(Clone)]
 pub struct RenderService {
     redis: RedisPool,
     config: Config,
-    compiled_cache: Arc<RwLock<HashMap<String, Tera>>>,
+    compiled_cache: Arc<CompiledCache>,
+    inflight: Arc<SingleFlight<Value>>,
+    cache_policy: CachePolicy,
+    cipher: Option<Arc<PayloadCipher>>,
+}
+
+/// Owned inputs of a render, so a refresh can outlive the request.
+struct RenderJob {
+    template_code: String,
+    version: i32,
+    language: String,
+    template_type: TemplateType,
+    content: String,
+    variables: HashMap<String, Value>,
+    cache_key: String,
+    ttl_secs: u64,
 }
 
 impl RenderService {
     pub fn new(redis: RedisPool, config: Config) -> Self {
+        let compiled_cache = Arc::new(CompiledCache::new(
+            config.compiled_cache_max_entries,
+            config.compiled_cache_max_bytes,
+        ));
         Self {
             redis,
+            cache_policy: CachePolicy::from_config(&config),
+            cipher: config
+                .rendered_cache_encryption_enabled
+                .then(|| Arc::new(PayloadCipher::from_secret(&config.secret_key))),
             config,
-            compiled_cache: Arc::new(RwLock::new(HashMap::new())),
+            compiled_cache,
+            inflight: Arc::new(SingleFlight::new()),
         }
     }
 
-    pub async fn render(
-        &self,
-        template_code: &str,
-        version: i32,
-        language: &str,
-        template_type: &TemplateType,
-        content: &str,
-        variables: &HashMap<String, Value>,
-    ) -> Result<Value, AppError> {
+    #[tracing::instrument(
+        skip_all,
+        fields(template_code = %template.template_code, version = template.version, language = %template.language)
+    )]
+    pub async fn render(&self, template: &Template, variables: &HashMap<String, Value>) -> Result<Value, AppError> {
+        let template_type: TemplateType = template.template_type
+            .parse()
+            .map_err(|e| render_failed("invalid_type", e))?;
+        let policy = self.cache_policy.for_meta(template.meta.as_ref());
+
         let var_hash = self.hash_variables(variables);
-        let cache_key = format!("rendered:{}:{}:{}:{}", template_code, version, language, var_hash);
+        let job = RenderJob {
+            template_code: template.template_code.clone(),
+            version: template.version,
+            language: template.language.clone(),
+            template_type,
+            content: template.content.clone(),
+            variables: variables.clone(),
+            cache_key: cache::rendered_key(&template.template_code, template.version, &template.language, &var_hash),
+            ttl_secs: policy.rendered_ttl_secs,
+        };
+
+        // Output that must never be cached (sensitive templates, e.g. OTP
+        // codes) skips Redis and coalescing entirely, so it is never shared
+        // between callers.
+        if job.ttl_secs == 0 {
+            return self.render_uncached(&job).await;
+        }
+
+        let stale_secs = self.config.cache_stale_while_revalidate_secs;
 
         let mut redis_conn = self.redis.clone();
-        if let Ok(Some(cached)) = redis_conn.get::<_, Option<String>>(&cache_key).await {
-            return serde_json::from_str(&cached)
-                .map_err(|e| AppError::InternalError(format!("Cache deserialize error: {}", e)));
+        let cached = match redis_conn.get::<_, Option<String>>(&job.cache_key).await {
+            Ok(Some(payload)) => self.open_payload(&job.cache_key, payload),
+            _ => None,
+        };
+        let stale = match cached {
+            Some(cached) => match cache::decode_entry::<Value>(&cached) {
+                Some(Cached::Fresh(rendered)) => {
+                    TEMPLATE_CACHE_HITS.with_label_values(&["rendered"]).inc();
+                    return Ok(rendered);
+                }
+                Some(Cached::Stale(rendered)) if stale_secs > 0 => Some(rendered),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if stale.is_some() {
+            TEMPLATE_CACHE_HITS.with_label_values(&["rendered"]).inc();
+        } else {
+            TEMPLATE_CACHE_MISSES.with_label_values(&["rendered"]).inc();
+        }
+
+        if let Some(rendered) = stale {
+            self.spawn_refresh(job);
+            return Ok(rendered);
+        }
+
+        self.inflight
+            .run(&job.cache_key, || self.render_and_store(&job))
+            .await
+    }
+
+    /// Re-renders a stale entry in the background; concurrent refreshes of
+    /// the same key are coalesced with foreground renders.
+    fn spawn_refresh(&self, job: RenderJob) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = this.inflight.run(&job.cache_key, || this.render_and_store(&job)).await {
+                tracing::warn!("Background re-render of {} failed: {}", job.cache_key, e);
+            }
+        });
+    }
+
+    async fn render_and_store(&self, job: &RenderJob) -> Result<Value, AppError> {
+        let rendered = self.render_uncached(job).await?;
+
+        if let Some((payload, ttl)) = cache::encode_entry(
+            &rendered,
+            job.ttl_secs,
+            self.config.cache_stale_while_revalidate_secs,
+        ) {
+            let payload = match &self.cipher {
+                Some(cipher) => cipher.encrypt(&job.cache_key, &payload),
+                None => payload,
+            };
+            let mut redis_conn = self.redis.clone();
+            let _ = cache::store_rendered(
+                &mut redis_conn,
+                &job.template_code,
+                job.version,
+                &job.language,
+                &job.cache_key,
+                &payload,
+                ttl,
+            )
+            .await;
+        }
+
+        Ok(rendered)
+    }
+
+    /// Decrypts a cached payload when encryption is on. Entries that fail to
+    /// decrypt are treated as misses and overwritten by the next render.
+    fn open_payload(&self, cache_key: &str, payload: String) -> Option<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(cache_key, &payload),
+            None => Some(payload),
         }
+    }
 
-        let rendered = match template_type {
-            TemplateType::EmailHtml => self.render_html(content, variables).await?,
-            TemplateType::PushJson => self.render_push_json(content, variables).await?,
+    #[tracing::instrument(name = "template.render", skip_all, fields(template.type = job.template_type.as_str()))]
+    async fn render_uncached(&self, job: &RenderJob) -> Result<Value, AppError> {
+        let start = Instant::now();
+        let rendered = match job.template_type {
+            TemplateType::EmailHtml => self.render_html(&job.content, &job.variables).await?,
+            TemplateType::PushJson => self.render_push_json(&job.content, &job.variables).await?,
         };
+        TEMPLATE_RENDER_DURATION
+            .with_label_values(&[job.template_type.as_str()])
+            .observe(start.elapsed().as_secs_f64());
 
         let rendered_str = serde_json::to_string(&rendered)
             .map_err(|e| AppError::InternalError(format!("Serialize error: {}", e)))?;
 
         let size_kb = rendered_str.len() / 1024;
         if size_kb > self.config.max_rendered_size_kb {
-            return Err(AppError::RenderedSizeExceeded);
+            return Err(render_failed("size_exceeded", AppError::RenderedSizeExceeded));
         }
 
-        let _: Result<(), redis::RedisError> = redis_conn
-            .set_ex(&cache_key, &rendered_str, self.config.rendered_cache_ttl_secs)
-            .await;
-
         Ok(rendered)
     }
 
+    /// Compiles `template` into the compiled cache ahead of its first render.
+    pub fn precompile(&self, template: &Template) -> Result<(), AppError> {
+        let template_type: TemplateType = template.template_type.parse()?;
+        let template_key = self.compiled_key(&template_type, &template.content);
+        self.compiled(&template_type, &template_key, &template.content).map(|_| ())
+    }
+
     async fn render_html(&self, content: &str, variables: &HashMap<String, Value>) -> Result<Value, AppError> {
-        let template_key = format!("html_{}", self.hash_content(content));
-        
-        let mut cache = self.compiled_cache.write().await;
-        let tera = cache.entry(template_key.clone()).or_insert_with(|| {
-            let mut t = Tera::default();
-            t.add_raw_template(&template_key, content).ok();
-            t
-        });
+        let template_key = self.compiled_key(&TemplateType::EmailHtml, content);
+        let tera = self.compiled(&TemplateType::EmailHtml, &template_key, content)?;
 
         let context = tera::Context::from_serialize(variables)
-            .map_err(|e| AppError::RenderError(format!("Context error: {}", e)))?;
+            .map_err(|e| template_failed(TemplateErrorDetails::new(TemplateErrorKind::Context, format!("Context error: {}", e))))?;
 
         let rendered_html = tera
             .render(&template_key, &context)
-            .map_err(|e| AppError::RenderError(format!("Tera render error: {}", e)))?;
+            .map_err(|e| template_failed(rendering::describe_tera_error(TemplateErrorKind::Render, &e, &template_key, content)))?;
 
         Ok(serde_json::json!({ "rendered": rendered_html }))
     }
 
     async fn render_push_json(&self, content: &str, variables: &HashMap<String, Value>) -> Result<Value, AppError> {
-        let template_key = format!("push_{}", self.hash_content(content));
-        
-        let mut cache = self.compiled_cache.write().await;
-        let tera = cache.entry(template_key.clone()).or_insert_with(|| {
-            let mut t = Tera::default();
-            t.add_raw_template(&template_key, content).ok();
-            t
-        });
+        let template_key = self.compiled_key(&TemplateType::PushJson, content);
+        let tera = self.compiled(&TemplateType::PushJson, &template_key, content)?;
 
         let context = tera::Context::from_serialize(variables)
-            .map_err(|e| AppError::RenderError(format!("Context error: {}", e)))?;
+            .map_err(|e| template_failed(TemplateErrorDetails::new(TemplateErrorKind::Context, format!("Context error: {}", e))))?;
 
         let rendered_str = tera
             .render(&template_key, &context)
-            .map_err(|e| AppError::RenderError(format!("Tera render error: {}", e)))?;
+            .map_err(|e| template_failed(rendering::describe_tera_error(TemplateErrorKind::Render, &e, &template_key, content)))?;
 
-        let rendered_json: Value = serde_json::from_str(&rendered_str)
-            .map_err(|e| AppError::RenderError(format!("Invalid JSON after render: {}", e)))?;
+        let rendered_json: Value = serde_json::from_str(&rendered_str).map_err(|e| {
+            template_failed(TemplateErrorDetails::new(TemplateErrorKind::InvalidOutput, format!("Invalid JSON after render: {}", e)))
+        })?;
 
         if !rendered_json.is_object() {
-            return Err(AppError::RenderError("Rendered push template must be a JSON object".to_string()));
+            return Err(template_failed(TemplateErrorDetails::new(
+                TemplateErrorKind::InvalidOutput,
+                "Rendered push template must be a JSON object",
+            )));
         }
 
         let obj = rendered_json.as_object().unwrap();
         if !obj.contains_key("title") || !obj.contains_key("body") {
-            return Err(AppError::RenderError("Push template must contain 'title' and 'body' fields".to_string()));
+            return Err(template_failed(TemplateErrorDetails::new(
+                TemplateErrorKind::InvalidOutput,
+                "Push template must contain 'title' and 'body' fields",
+            )));
         }
 
         Ok(serde_json::json!({ "rendered": rendered_json }))
     }
 
+    fn compiled_key(&self, template_type: &TemplateType, content: &str) -> String {
+        match template_type {
+            TemplateType::EmailHtml => format!("html_{}", self.hash_content(content)),
+            TemplateType::PushJson => format!("push_{}", self.hash_content(content)),
+        }
+    }
+
+    /// Returns the compiled template for `template_key`, compiling and caching
+    /// it on first use. Compilation happens outside any lock.
+    fn compiled(&self, template_type: &TemplateType, template_key: &str, content: &str) -> Result<Arc<Tera>, AppError> {
+        if let Some(tera) = self.compiled_cache.get(template_key) {
+            return Ok(tera);
+        }
+
+        let _span = tracing::info_span!("template.compile", template.type = template_type.as_str()).entered();
+        let start = Instant::now();
+        let tera = rendering::compile(template_key, content).map_err(|details| template_failed(*details))?;
+        TEMPLATE_COMPILE_DURATION
+            .with_label_values(&[template_type.as_str()])
+            .observe(start.elapsed().as_secs_f64());
+
+        let tera = Arc::new(tera);
+        self.compiled_cache
+            .insert(template_key.to_string(), tera.clone(), template_key.len() + content.len());
+        Ok(tera)
+    }
+
     fn hash_variables(&self, variables: &HashMap<String, Value>) -> String {
         let mut hasher = Sha256::new();
         let serialized = serde_json::to_string(variables).unwrap_or_default();
@@ -128,19 +286,56 @@ impl RenderService {
         hex::encode(hasher.finalize())[..16].to_string()
     }
 
-    pub async fn invalidate_cache(&self, template_code: &str, version: i32, language: &str) -> Result<(), AppError> {
+    pub async fn cached_rendered_keys(&self, template: &Template) -> Result<Vec<CacheKeyInfo>, AppError> {
         let mut redis_conn = self.redis.clone();
-        let pattern = format!("rendered:{}:{}:{}:*", template_code, version, language);
-        
-        let keys: Vec<String> = redis_conn
-            .keys(&pattern)
-            .await
-            .unwrap_or_default();
+        let keys = cache::rendered_members(
+            &mut redis_conn,
+            &template.template_code,
+            template.version,
+            &template.language,
+        )
+        .await?;
+        Ok(cache::describe_keys(&mut redis_conn, &keys).await?)
+    }
 
-        for key in keys {
-            let _: Result<(), redis::RedisError> = redis_conn.del(&key).await;
-        }
+    pub fn is_compiled(&self, template: &Template) -> bool {
+        template.template_type
+            .parse::<TemplateType>()
+            .map(|t| self.compiled_cache.contains(&self.compiled_key(&t, &template.content)))
+            .unwrap_or(false)
+    }
 
-        Ok(())
+    pub fn evict_compiled(&self, template: &Template) -> bool {
+        template.template_type
+            .parse::<TemplateType>()
+            .map(|t| self.compiled_cache.remove(&self.compiled_key(&t, &template.content)))
+            .unwrap_or(false)
     }
+
+    /// Deletes every rendered entry and index from Redis and empties the
+    /// compiled cache. Returns (Redis keys deleted, compiled templates dropped).
+    pub async fn flush_cache(&self) -> Result<(usize, usize), AppError> {
+        let mut redis_conn = self.redis.clone();
+        let mut deleted = cache::delete_matching(&mut redis_conn, "rendered:*").await?.len();
+        deleted += cache::delete_matching(&mut redis_conn, "rendered_index:*").await?.len();
+
+        Ok((deleted, self.compiled_cache.clear()))
+    }
+}
+
+/// Counts a failed render by kind and passes the error through.
+fn render_failed(kind: &str, err: AppError) -> AppError {
+    TEMPLATE_RENDER_ERRORS.with_label_values(&[kind]).inc();
+    err
+}
+
+/// Counts a template failure by its kind; compile failures are syntax errors.
+fn template_failed(details: TemplateErrorDetails) -> AppError {
+    let kind = details.kind;
+    let err = if kind == TemplateErrorKind::Compile {
+        AppError::TemplateSyntaxError(Box::new(details))
+    } else {
+        AppError::RenderError(Box::new(details))
+    };
+    render_failed(kind.as_str(), err)
 }
\ No newline at end of file
@@ -1,27 +1,70 @@
-use crate::cache::RedisPool;
+use crate::cache::{self, CachePolicy, Cached, RedisPool};
+use crate::config::Config;
 use crate::db::DbPool;
 use crate::error::AppError;
-use crate::models::{CreateTemplateRequest, Template, TemplateType};
+use crate::local_cache::LocalCache;
+use crate::middleware::metrics::{TEMPLATE_CACHE_HITS, TEMPLATE_CACHE_MISSES};
+use crate::models::{CacheKeyInfo, CreateTemplateRequest, Template, TemplateType, WebhookEvent};
+use crate::rendering;
+use crate::services::outbox_dispatcher;
+use crate::singleflight::SingleFlight;
+use crate::telemetry::DbQuery;
 use redis::AsyncCommands;
 use sqlx::Row;
+use std::sync::Arc;
+use std::time::Duration;
 
+#[derive(Clone)]
 pub struct TemplateService {
     pool: DbPool,
     redis: RedisPool,
+    local_cache: Arc<LocalCache<Template>>,
+    inflight: Arc<SingleFlight<Template>>,
+    cache_policy: CachePolicy,
+    stale_secs: u64,
 }
 
 impl TemplateService {
-    pub fn new(pool: DbPool, redis: RedisPool) -> Self {
-        Self { pool, redis }
+    pub fn new(pool: DbPool, redis: RedisPool, config: &Config) -> Self {
+        Self {
+            pool,
+            redis,
+            local_cache: Arc::new(LocalCache::new(
+                config.template_local_cache_capacity,
+                Duration::from_secs(config.template_local_cache_ttl_secs),
+            )),
+            inflight: Arc::new(SingleFlight::new()),
+            cache_policy: CachePolicy::from_config(config),
+            stale_secs: config.cache_stale_while_revalidate_secs,
+        }
     }
 
+    /// Keeps this replica's in-process cache coherent with invalidations
+    /// published by any replica.
+    pub fn spawn_invalidation_listener(&self) {
+        let on_keys = self.local_cache.clone();
+        let on_resubscribe = self.local_cache.clone();
+        cache::spawn_invalidation_listener(
+            self.redis.clone(),
+            move |keys| {
+                for key in keys {
+                    on_keys.remove(&key);
+                }
+            },
+            move || on_resubscribe.clear(),
+        );
+    }
+
+    #[tracing::instrument(skip_all, fields(template_code = %req.template_code, language = %req.language))]
     pub async fn create_template(&self, req: CreateTemplateRequest) -> Result<Template, AppError> {
-        let template_type = TemplateType::from_str(&req.template_type)
-            .ok_or(AppError::InvalidTemplateType)?;
+        let template_type: TemplateType = req.template_type.parse()?;
 
         self.validate_content(&template_type, &req.content)?;
+        rendering::compile(&req.template_code, &req.content).map_err(AppError::TemplateSyntaxError)?;
+        cache::parse_cache_overrides(req.meta.as_ref()).map_err(AppError::InvalidContent)?;
+        cache::is_sensitive(req.meta.as_ref()).map_err(AppError::InvalidContent)?;
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.pool.begin().db_query("begin").await?;
 
         let max_version: Option<i32> = sqlx::query(
             "SELECT MAX(version) as max_ver FROM templates WHERE template_code = $1 AND language = $2"
@@ -29,6 +72,7 @@ impl TemplateService {
         .bind(&req.template_code)
         .bind(&req.language)
         .fetch_optional(&mut *tx)
+        .db_query("select_max_version")
         .await?
         .and_then(|row| row.try_get("max_ver").ok());
 
@@ -48,15 +92,21 @@ impl TemplateService {
         .bind(&req.content)
         .bind(&req.meta)
         .fetch_one(&mut *tx)
+        .db_query("insert_template")
         .await?;
 
-        tx.commit().await?;
+        outbox_dispatcher::record(&mut tx, &WebhookEvent::template_created(&template)).await?;
+
+        tx.commit().db_query("commit").await?;
 
-        self.invalidate_template_cache(&req.template_code, new_version, &req.language).await?;
+        // The outbox dispatcher invalidates too; doing it here as well keeps
+        // this instance consistent for an immediate read.
+        self.invalidate_after_write(&req.template_code, new_version, &req.language).await;
 
         Ok(template)
     }
 
+    #[tracing::instrument(skip(self))]
     pub async fn get_template(
         &self,
         template_code: &str,
@@ -65,19 +115,62 @@ impl TemplateService {
     ) -> Result<Template, AppError> {
         let lang = language.unwrap_or("en");
 
-        let cache_key = if let Some(ver) = version {
-            format!("template:{}:{}:{}", template_code, ver, lang)
-        } else {
-            format!("template:{}:latest:{}", template_code, lang)
-        };
+        let cache_key = cache::template_key(template_code, version, lang);
+
+        if let Some(template) = self.local_cache.get(&cache_key) {
+            TEMPLATE_CACHE_HITS.with_label_values(&["template_local"]).inc();
+            return Ok(template);
+        }
+        TEMPLATE_CACHE_MISSES.with_label_values(&["template_local"]).inc();
 
         let mut redis_conn = self.redis.clone();
         if let Ok(Some(cached)) = redis_conn.get::<_, Option<String>>(&cache_key).await {
-            if let Ok(template) = serde_json::from_str::<Template>(&cached) {
-                return Ok(template);
+            match cache::decode_entry::<Template>(&cached) {
+                Some(Cached::Fresh(template)) => {
+                    TEMPLATE_CACHE_HITS.with_label_values(&["template"]).inc();
+                    self.local_cache.insert(cache_key, template.clone());
+                    return Ok(template);
+                }
+                Some(Cached::Stale(template)) if self.stale_secs > 0 => {
+                    TEMPLATE_CACHE_HITS.with_label_values(&["template"]).inc();
+                    self.spawn_refresh(template_code, lang, version, cache_key);
+                    return Ok(template);
+                }
+                _ => {}
             }
         }
+        TEMPLATE_CACHE_MISSES.with_label_values(&["template"]).inc();
+
+        self.inflight
+            .run(&cache_key, || self.load_template(template_code, lang, version, &cache_key))
+            .await
+    }
 
+    /// Refreshes a stale entry in the background; concurrent refreshes of
+    /// the same key are coalesced with foreground loads.
+    fn spawn_refresh(&self, template_code: &str, lang: &str, version: Option<i32>, cache_key: String) {
+        let this = self.clone();
+        let template_code = template_code.to_string();
+        let lang = lang.to_string();
+
+        tokio::spawn(async move {
+            let result = this
+                .inflight
+                .run(&cache_key, || this.load_template(&template_code, &lang, version, &cache_key))
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("Background refresh of {} failed: {}", cache_key, e);
+            }
+        });
+    }
+
+    async fn load_template(
+        &self,
+        template_code: &str,
+        lang: &str,
+        version: Option<i32>,
+        cache_key: &str,
+    ) -> Result<Template, AppError> {
         let template = if let Some(ver) = version {
             sqlx::query_as::<_, Template>(
                 "SELECT * FROM templates WHERE template_code = $1 AND language = $2 AND version = $3 AND is_active = true"
@@ -86,6 +179,7 @@ impl TemplateService {
             .bind(lang)
             .bind(ver)
             .fetch_optional(&self.pool)
+            .db_query("select_template")
             .await?
         } else {
             sqlx::query_as::<_, Template>(
@@ -94,16 +188,60 @@ impl TemplateService {
             .bind(template_code)
             .bind(lang)
             .fetch_optional(&self.pool)
+            .db_query("select_template")
             .await?
         };
 
         let template = template.ok_or(AppError::TemplateNotFound)?;
+        self.cache_template(cache_key, &template).await;
 
-        if let Ok(serialized) = serde_json::to_string(&template) {
-            let _: Result<(), redis::RedisError> = redis_conn.set_ex(&cache_key, &serialized, 3600).await;
+        Ok(template)
+    }
+
+    async fn cache_template(&self, cache_key: &str, template: &Template) {
+        let policy = self.cache_policy.for_meta(template.meta.as_ref());
+        if let Some((payload, ttl)) = cache::encode_entry(template, policy.template_ttl_secs, self.stale_secs) {
+            let mut redis_conn = self.redis.clone();
+            let _: Result<(), redis::RedisError> = redis_conn.set_ex(cache_key, &payload, ttl).await;
+            self.local_cache.insert(cache_key.to_string(), template.clone());
         }
+    }
 
-        Ok(template)
+    /// Caches `template` under its versioned key, then loads the `latest`
+    /// key from Postgres: `template` may already have been superseded or
+    /// deleted, so it is never cached as the latest version directly.
+    pub async fn warm(&self, template: &Template) -> Result<(), AppError> {
+        let versioned_key = cache::template_key(&template.template_code, Some(template.version), &template.language);
+        self.cache_template(&versioned_key, template).await;
+
+        let latest_key = cache::template_key(&template.template_code, None, &template.language);
+        let result = self
+            .inflight
+            .run(&latest_key, || {
+                self.load_template(&template.template_code, &template.language, None, &latest_key)
+            })
+            .await;
+        match result {
+            Ok(_) | Err(AppError::TemplateNotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Latest active version of every (template_code, language) pair.
+    pub async fn latest_active(&self) -> Result<Vec<Template>, AppError> {
+        let templates = sqlx::query_as::<_, Template>(
+            r#"
+            SELECT DISTINCT ON (template_code, language) *
+            FROM templates
+            WHERE is_active = true
+            ORDER BY template_code, language, version DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .db_query("select_latest_active")
+        .await?;
+
+        Ok(templates)
     }
 
     pub async fn get_versions(&self, template_code: &str) -> Result<Vec<Template>, AppError> {
@@ -112,18 +250,22 @@ impl TemplateService {
         )
         .bind(template_code)
         .fetch_all(&self.pool)
+        .db_query("select_versions")
         .await?;
 
         Ok(templates)
     }
 
     pub async fn soft_delete(&self, template_code: &str, version: i32) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().db_query("begin").await?;
+
         let result = sqlx::query(
             "UPDATE templates SET is_active = false WHERE template_code = $1 AND version = $2"
         )
         .bind(template_code)
         .bind(version)
-        .execute(&self.pool)
+        .execute(&mut *tx)
+        .db_query("soft_delete")
         .await?;
 
         if result.rows_affected() == 0 {
@@ -135,37 +277,113 @@ impl TemplateService {
         )
         .bind(template_code)
         .bind(version)
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *tx)
+        .db_query("select_languages")
         .await?;
 
+        outbox_dispatcher::record(&mut tx, &WebhookEvent::template_deleted(template_code, version, &languages)).await?;
+
+        tx.commit().db_query("commit").await?;
+
         for lang in languages {
-            self.invalidate_template_cache(template_code, version, &lang).await?;
+            self.invalidate_after_write(template_code, version, &lang).await;
         }
 
         Ok(())
     }
 
-    async fn invalidate_template_cache(&self, template_code: &str, version: i32, language: &str) -> Result<(), AppError> {
+    /// The cached `template:` entry for a version (or `latest` if `None`),
+    /// if one exists.
+    pub async fn cached_template_key(
+        &self,
+        template_code: &str,
+        version: Option<i32>,
+        language: &str,
+    ) -> Result<Option<CacheKeyInfo>, AppError> {
+        let mut redis_conn = self.redis.clone();
+        let key = cache::template_key(template_code, version, language);
+        let mut described = cache::describe_keys(&mut redis_conn, &[key]).await?;
+        Ok(described.pop())
+    }
+
+    /// Drops cached template and rendered entries for the matching versions
+    /// and returns them. Fails if nothing matches.
+    pub async fn purge_cache(
+        &self,
+        template_code: &str,
+        version: Option<i32>,
+        language: Option<&str>,
+    ) -> Result<Vec<Template>, AppError> {
+        let templates: Vec<Template> = self
+            .get_versions(template_code)
+            .await?
+            .into_iter()
+            .filter(|t| version.is_none_or(|v| t.version == v))
+            .filter(|t| language.is_none_or(|l| t.language == l))
+            .collect();
+
+        if templates.is_empty() {
+            return Err(AppError::TemplateNotFound);
+        }
+
+        for template in &templates {
+            self.invalidate_template_cache(&template.template_code, template.version, &template.language)
+                .await?;
+        }
+
+        Ok(templates)
+    }
+
+    /// Deletes every `template:` key, locally and in Redis, and tells other
+    /// replicas to drop theirs. Returns the number of Redis keys deleted.
+    pub async fn flush_cache(&self) -> Result<usize, AppError> {
+        self.local_cache.clear();
+
+        let mut redis_conn = self.redis.clone();
+        let deleted = cache::delete_matching(&mut redis_conn, "template:*").await?;
+
+        for keys in deleted.chunks(500) {
+            if let Err(e) = cache::publish_invalidation(&mut redis_conn, keys).await {
+                tracing::warn!("Failed to publish cache invalidation: {}", e);
+            }
+        }
+
+        Ok(deleted.len())
+    }
+
+    /// Drops the cached template and rendered entries for one version and
+    /// language, here and, through pub/sub, on other replicas. The local
+    /// entries are always dropped; a Redis failure is returned so that the
+    /// outbox dispatcher retries the event.
+    pub async fn invalidate_template_cache(&self, template_code: &str, version: i32, language: &str) -> Result<(), AppError> {
         let mut redis_conn = self.redis.clone();
         let keys = vec![
-            format!("template:{}:{}:{}", template_code, version, language),
-            format!("template:{}:latest:{}", template_code, language),
+            cache::template_key(template_code, Some(version), language),
+            cache::template_key(template_code, None, language),
         ];
 
-        for key in keys {
-            let _: Result<(), redis::RedisError> = redis_conn.del(&key).await;
+        for key in &keys {
+            self.local_cache.remove(key);
         }
-
-        let pattern = format!("rendered:{}:{}:{}:*", template_code, version, language);
-        let render_keys: Vec<String> = redis_conn.keys(&pattern).await.unwrap_or_default();
-        
-        for key in render_keys {
-            let _: Result<(), redis::RedisError> = redis_conn.del(&key).await;
+        for key in &keys {
+            let _: () = redis_conn.del(key).await?;
         }
 
+        cache::publish_invalidation(&mut redis_conn, &keys).await?;
+        cache::invalidate_rendered(&mut redis_conn, template_code, version, language).await?;
+
         Ok(())
     }
 
+    /// Invalidation right after a committed write. It only keeps this
+    /// instance consistent for an immediate read; the outbox dispatcher
+    /// retries it, so a failure must not fail the write.
+    async fn invalidate_after_write(&self, template_code: &str, version: i32, language: &str) {
+        if let Err(e) = self.invalidate_template_cache(template_code, version, language).await {
+            tracing::warn!("Cache invalidation for {} v{} deferred to the outbox: {}", template_code, version, e);
+        }
+    }
+
     fn validate_content(&self, template_type: &TemplateType, content: &str) -> Result<(), AppError> {
         match template_type {
             TemplateType::EmailHtml => {
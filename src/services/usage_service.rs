@@ -0,0 +1,314 @@
+use crate::cache::{self, RedisPool};
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::models::{Template, TemplateUsage};
+use crate::telemetry::DbQuery;
+use chrono::{DateTime, TimeZone, Utc};
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Renders not yet flushed to Postgres. Every usage key shares the
+/// `{template_usage}` hash tag so a batch can be RENAMEd in cluster mode.
+pub const PENDING_USAGE_KEY: &str = "{template_usage}:pending";
+/// Claimed batches are `{template_usage}:flushing:<claimed at ms>:<uuid>`.
+pub const FLUSHING_USAGE_PREFIX: &str = "{template_usage}:flushing:";
+const FLUSHING_KEY_TTL_SECS: i64 = 86_400;
+/// A batch claimed longer ago than this was left by a flush that died
+/// between claiming and deleting it, and is recovered by the next flush.
+pub const STALE_BATCH_SECS: u64 = 300;
+
+const COUNT_PREFIX: &str = "c:";
+const LAST_PREFIX: &str = "l:";
+
+/// Renders of one template version counted since the last flush.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageDelta {
+    pub template_code: String,
+    pub version: i32,
+    pub language: String,
+    pub renders: i64,
+    pub last_rendered_at: DateTime<Utc>,
+}
+
+/// Counts renders per template version in Redis and periodically folds
+/// the counts into the `template_usage` table, so the render path never
+/// waits on Postgres.
+#[derive(Clone)]
+pub struct UsageService {
+    pool: DbPool,
+    redis: RedisPool,
+    enabled: bool,
+}
+
+impl UsageService {
+    pub fn new(pool: DbPool, redis: RedisPool, enabled: bool) -> Self {
+        Self { pool, redis, enabled }
+    }
+
+    /// Records a render in the background. Renders are lost, not retried,
+    /// while Redis is unavailable.
+    pub fn record_render(&self, template: &Template) {
+        if !self.enabled {
+            return;
+        }
+        let field = usage_field(&template.template_code, template.version, &template.language);
+        let mut redis_conn = self.redis.clone();
+        tokio::spawn(async move {
+            let result: Result<(), redis::RedisError> = redis::pipe()
+                .hincr(PENDING_USAGE_KEY, format!("{}{}", COUNT_PREFIX, field), 1)
+                .ignore()
+                .hset(PENDING_USAGE_KEY, format!("{}{}", LAST_PREFIX, field), Utc::now().timestamp_millis())
+                .ignore()
+                .query_async(&mut redis_conn)
+                .await;
+            if let Err(e) = result {
+                tracing::debug!("Failed to record template usage: {}", e);
+            }
+        });
+    }
+
+    /// Moves pending counts to Postgres and returns how many template
+    /// versions were updated. Counts are put back if the write fails.
+    pub async fn flush(&self) -> Result<usize, AppError> {
+        let mut redis_conn = self.redis.clone();
+        if !redis_conn.exists::<_, bool>(PENDING_USAGE_KEY).await? {
+            return Ok(0);
+        }
+
+        match self.claim(PENDING_USAGE_KEY).await? {
+            Some(batch_key) => self.flush_batch(&batch_key).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Flushes batches claimed more than `stale_after` ago, which a replica
+    /// died before finishing, and returns how many template versions were
+    /// updated. Delivery is at least once: a flush that died after writing
+    /// to Postgres but before deleting its batch is counted twice.
+    pub async fn recover_batches(&self, stale_after: Duration) -> Result<usize, AppError> {
+        let mut redis_conn = self.redis.clone();
+        let pattern = format!("{}*", FLUSHING_USAGE_PREFIX);
+        let now = Utc::now().timestamp_millis();
+
+        let mut updated = 0;
+        for key in cache::keys_matching(&mut redis_conn, &pattern).await? {
+            let claimed_at = key
+                .strip_prefix(FLUSHING_USAGE_PREFIX)
+                .and_then(|rest| rest.split(':').next())
+                .and_then(|millis| millis.parse::<i64>().ok());
+            if claimed_at.is_some_and(|t| now - t < stale_after.as_millis() as i64) {
+                continue;
+            }
+            // Re-claiming under a new key means only one replica recovers
+            // each batch, and a recovery that dies is itself recovered.
+            if let Some(batch_key) = self.claim(&key).await? {
+                tracing::warn!("Recovering usage batch {} left by an interrupted flush", key);
+                updated += self.flush_batch(&batch_key).await?;
+            }
+        }
+        Ok(updated)
+    }
+
+    /// RENAMEs `key` to a new batch key and returns it, or `None` if `key`
+    /// no longer exists. RENAME claims atomically, so concurrent renders
+    /// start a new pending hash and replicas never share a batch.
+    async fn claim(&self, key: &str) -> Result<Option<String>, AppError> {
+        let mut redis_conn = self.redis.clone();
+        let batch_key = format!("{}{}:{}", FLUSHING_USAGE_PREFIX, Utc::now().timestamp_millis(), Uuid::new_v4());
+        let renamed: Result<(), redis::RedisError> = redis::cmd("RENAME")
+            .arg(key)
+            .arg(&batch_key)
+            .query_async(&mut redis_conn)
+            .await;
+        match renamed {
+            Ok(()) => {}
+            Err(e) if e.kind() == redis::ErrorKind::ResponseError => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let _: Result<(), redis::RedisError> = redis_conn.expire(&batch_key, FLUSHING_KEY_TTL_SECS).await;
+        Ok(Some(batch_key))
+    }
+
+    async fn flush_batch(&self, batch_key: &str) -> Result<usize, AppError> {
+        let mut redis_conn = self.redis.clone();
+        let fields: HashMap<String, String> = redis_conn.hgetall(batch_key).await?;
+        let deltas = parse_usage_batch(&fields, Utc::now());
+
+        if let Err(e) = self.apply(&deltas).await {
+            self.restore(&deltas).await;
+            let _: Result<(), redis::RedisError> = redis_conn.del(batch_key).await;
+            return Err(e);
+        }
+
+        let _: Result<(), redis::RedisError> = redis_conn.del(batch_key).await;
+        Ok(deltas.len())
+    }
+
+    async fn apply(&self, deltas: &[UsageDelta]) -> Result<(), AppError> {
+        if deltas.is_empty() {
+            return Ok(());
+        }
+
+        let codes: Vec<&str> = deltas.iter().map(|d| d.template_code.as_str()).collect();
+        let versions: Vec<i32> = deltas.iter().map(|d| d.version).collect();
+        let languages: Vec<&str> = deltas.iter().map(|d| d.language.as_str()).collect();
+        let renders: Vec<i64> = deltas.iter().map(|d| d.renders).collect();
+        let last_rendered: Vec<DateTime<Utc>> = deltas.iter().map(|d| d.last_rendered_at).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO template_usage (template_code, version, language, render_count, last_rendered_at)
+            SELECT u.template_code, u.version, u.language, u.render_count, u.last_rendered_at
+            FROM UNNEST($1::text[], $2::int[], $3::text[], $4::bigint[], $5::timestamptz[])
+                AS u (template_code, version, language, render_count, last_rendered_at)
+            JOIN templates t USING (template_code, version, language)
+            ON CONFLICT (template_code, version, language) DO UPDATE SET
+                render_count = template_usage.render_count + EXCLUDED.render_count,
+                last_rendered_at = GREATEST(template_usage.last_rendered_at, EXCLUDED.last_rendered_at),
+                updated_at = now()
+            "#
+        )
+        .bind(&codes)
+        .bind(&versions)
+        .bind(&languages)
+        .bind(&renders)
+        .bind(&last_rendered)
+        .execute(&self.pool)
+        .db_query("upsert_usage")
+        .await?;
+
+        Ok(())
+    }
+
+    async fn restore(&self, deltas: &[UsageDelta]) {
+        let mut pipe = redis::pipe();
+        for delta in deltas {
+            let field = usage_field(&delta.template_code, delta.version, &delta.language);
+            pipe.hincr(PENDING_USAGE_KEY, format!("{}{}", COUNT_PREFIX, field), delta.renders)
+                .ignore()
+                .hset_nx(PENDING_USAGE_KEY, format!("{}{}", LAST_PREFIX, field), delta.last_rendered_at.timestamp_millis())
+                .ignore();
+        }
+        let mut redis_conn = self.redis.clone();
+        let result: Result<(), redis::RedisError> = pipe.query_async(&mut redis_conn).await;
+        if let Err(e) = result {
+            tracing::error!("Lost usage counts for {} template versions: {}", deltas.len(), e);
+        }
+    }
+
+    pub fn spawn_flush(&self, interval_secs: u64) {
+        if !self.enabled {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+            let mut last_recovery: Option<Instant> = None;
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if !service.redis.is_available() {
+                    continue;
+                }
+                // SCAN walks the whole keyspace, so leftovers are looked for
+                // once per staleness window rather than on every flush.
+                let stale_after = Duration::from_secs(STALE_BATCH_SECS);
+                if last_recovery.is_none_or(|t| t.elapsed() >= stale_after) {
+                    last_recovery = Some(Instant::now());
+                    match service.recover_batches(stale_after).await {
+                        Ok(0) => {}
+                        Ok(count) => tracing::info!("Recovered usage for {} template versions", count),
+                        Err(e) => tracing::warn!("Template usage recovery failed: {}", e),
+                    }
+                }
+                match service.flush().await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::debug!("Flushed usage for {} template versions", count),
+                    Err(e) => tracing::warn!("Template usage flush failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Usage of every version of a template, including versions that were
+    /// never rendered.
+    pub async fn usage_for(&self, template_code: &str) -> Result<Vec<TemplateUsage>, AppError> {
+        let usage = sqlx::query_as::<_, TemplateUsage>(
+            r#"
+            SELECT t.template_code, t.version, t.language,
+                   COALESCE(u.render_count, 0) AS render_count, u.last_rendered_at
+            FROM templates t
+            LEFT JOIN template_usage u USING (template_code, version, language)
+            WHERE t.template_code = $1
+            ORDER BY t.version DESC, t.language ASC
+            "#
+        )
+        .bind(template_code)
+        .fetch_all(&self.pool)
+        .db_query("select_usage")
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Active versions not rendered within `days` days, never-rendered and
+    /// longest-idle first.
+    pub async fn unused(&self, days: i32) -> Result<Vec<TemplateUsage>, AppError> {
+        let usage = sqlx::query_as::<_, TemplateUsage>(
+            r#"
+            SELECT t.template_code, t.version, t.language,
+                   COALESCE(u.render_count, 0) AS render_count, u.last_rendered_at
+            FROM templates t
+            LEFT JOIN template_usage u USING (template_code, version, language)
+            WHERE t.is_active = true
+              AND (u.last_rendered_at IS NULL OR u.last_rendered_at < now() - make_interval(days => $1))
+            ORDER BY u.last_rendered_at ASC NULLS FIRST, t.template_code, t.version DESC, t.language
+            "#
+        )
+        .bind(days)
+        .fetch_all(&self.pool)
+        .db_query("select_unused")
+        .await?;
+
+        Ok(usage)
+    }
+}
+
+/// Hash field naming one template version, unambiguous whatever characters
+/// the code or language contain.
+pub fn usage_field(template_code: &str, version: i32, language: &str) -> String {
+    serde_json::json!([template_code, version, language]).to_string()
+}
+
+/// Turns a claimed pending hash into per-version deltas. Malformed fields
+/// are dropped; a count without a timestamp is dated `flushed_at`.
+pub fn parse_usage_batch(fields: &HashMap<String, String>, flushed_at: DateTime<Utc>) -> Vec<UsageDelta> {
+    let mut deltas: Vec<UsageDelta> = fields
+        .iter()
+        .filter_map(|(name, value)| {
+            let field = name.strip_prefix(COUNT_PREFIX)?;
+            let (template_code, version, language) =
+                serde_json::from_str::<(String, i32, String)>(field).ok()?;
+            let renders = value.parse::<i64>().ok().filter(|n| *n > 0)?;
+            let last_rendered_at = fields
+                .get(&format!("{}{}", LAST_PREFIX, field))
+                .and_then(|ms| ms.parse::<i64>().ok())
+                .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+                .unwrap_or(flushed_at);
+            Some(UsageDelta {
+                template_code,
+                version,
+                language,
+                renders,
+                last_rendered_at,
+            })
+        })
+        .collect();
+
+    deltas.sort_by(|a, b| {
+        (&a.template_code, a.version, &a.language).cmp(&(&b.template_code, b.version, &b.language))
+    });
+    deltas
+}
\ No newline at end of file
@@ -0,0 +1,467 @@
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::middleware::signature;
+use crate::models::{webhook_events, CreateWebhookRequest, WebhookDelivery, WebhookEvent, WebhookSubscription};
+use crate::telemetry::DbQuery;
+use chrono::Utc;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sqlx::FromRow;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const SECRET_PREFIX: &str = "whsec_";
+const MAX_ERROR_LEN: usize = 500;
+const MAX_RESPONSE_BYTES: usize = 4096;
+const MAX_LOG_ENTRIES: i64 = 100;
+
+/// A claimed delivery, joined with where and how to send it.
+#[derive(Debug, FromRow)]
+struct DueDelivery {
+    id: Uuid,
+    event_id: Uuid,
+    event_type: String,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+/// Result of one POST to a subscriber. Only 2xx responses succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryAttempt {
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl DeliveryAttempt {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Webhook subscriptions and their deliveries. Events fan out to one
+/// `webhook_deliveries` row per matching subscription, which a background
+/// worker sends with retries; the rows double as the delivery log.
+#[derive(Clone)]
+pub struct WebhookService {
+    pool: DbPool,
+    client: reqwest::Client,
+    enabled: bool,
+    poll_interval_secs: u64,
+    batch_size: i64,
+    max_attempts: i32,
+    backoff_base_secs: u64,
+    backoff_max_secs: u64,
+    timeout_secs: u64,
+    allow_private_targets: bool,
+}
+
+impl WebhookService {
+    pub fn new(pool: DbPool, config: &Config) -> Self {
+        Self {
+            pool,
+            client: http_client(config.webhook_timeout_secs, config.webhook_allow_private_targets),
+            enabled: config.webhook_worker_enabled,
+            poll_interval_secs: config.webhook_poll_interval_secs,
+            batch_size: config.webhook_batch_size,
+            max_attempts: config.webhook_max_attempts,
+            backoff_base_secs: config.webhook_backoff_base_secs,
+            backoff_max_secs: config.webhook_backoff_max_secs,
+            timeout_secs: config.webhook_timeout_secs,
+            allow_private_targets: config.webhook_allow_private_targets,
+        }
+    }
+
+    /// Creates a subscription with a generated signing secret.
+    pub async fn create_subscription(&self, req: CreateWebhookRequest) -> Result<WebhookSubscription, AppError> {
+        let url = reqwest::Url::parse(req.url.trim())
+            .map_err(|e| AppError::InvalidContent(format!("Invalid webhook URL: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AppError::InvalidContent("Webhook URL must use http or https".to_string()));
+        }
+        if !self.allow_private_targets {
+            check_public_target(&url).await?;
+        }
+        if req.event_types.is_empty() {
+            return Err(AppError::InvalidContent("At least one event type is required".to_string()));
+        }
+        if let Some(event_type) = req.event_types.iter().find(|e| !webhook_events::ALL.contains(&e.as_str())) {
+            return Err(AppError::InvalidContent(format!("Unknown event type: {}", event_type)));
+        }
+
+        let secret = format!("{}{}", SECRET_PREFIX, Uuid::new_v4().simple());
+
+        let subscription = sqlx::query_as::<_, WebhookSubscription>(
+            r#"
+            INSERT INTO webhook_subscriptions (url, secret, event_types, description)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#
+        )
+        .bind(url.as_str())
+        .bind(&secret)
+        .bind(&req.event_types)
+        .bind(&req.description)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(subscription)
+    }
+
+    pub async fn list_subscriptions(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+        let subscriptions = sqlx::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM webhook_subscriptions ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    /// Deactivates a subscription and cancels its pending deliveries. The
+    /// delivery log is kept.
+    pub async fn deactivate_subscription(&self, id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE webhook_subscriptions SET is_active = false, deactivated_at = now() WHERE id = $1 AND is_active = true"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::WebhookNotFound);
+        }
+
+        sqlx::query(
+            "UPDATE webhook_deliveries SET status = 'cancelled' WHERE subscription_id = $1 AND status = 'pending'"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Most recent deliveries for a subscription, newest first.
+    pub async fn deliveries(&self, subscription_id: Uuid) -> Result<Vec<WebhookDelivery>, AppError> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM webhook_subscriptions WHERE id = $1)")
+            .bind(subscription_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if !exists {
+            return Err(AppError::WebhookNotFound);
+        }
+
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE subscription_id = $1 ORDER BY created_at DESC LIMIT $2"
+        )
+        .bind(subscription_id)
+        .bind(MAX_LOG_ENTRIES)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Queues `event` for every active subscription to its type and returns
+    /// how many deliveries were queued. Queuing the same event twice is a
+    /// no-op.
+    pub async fn enqueue(&self, event: &WebhookEvent) -> Result<u64, AppError> {
+        let payload = serde_json::to_value(event)
+            .map_err(|e| AppError::InternalError(format!("Serialize error: {}", e)))?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (subscription_id, event_id, event_type, payload)
+            SELECT id, $1, $2, $3
+            FROM webhook_subscriptions
+            WHERE is_active = true AND $2 = ANY(event_types)
+            ON CONFLICT (subscription_id, event_id) DO NOTHING
+            "#
+        )
+        .bind(event.id)
+        .bind(&event.event_type)
+        .bind(&payload)
+        .execute(&self.pool)
+        .db_query("enqueue_webhook")
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Claims due deliveries, sends them concurrently and records the
+    /// outcomes. Returns how many were attempted.
+    pub async fn dispatch_due(&self) -> Result<usize, AppError> {
+        // Claimed rows are leased past the request timeout, so other
+        // replicas skip them and a crash mid-send only delays a retry.
+        let lease_secs = (self.timeout_secs * 2 + 30) as f64;
+        let due = sqlx::query_as::<_, DueDelivery>(
+            r#"
+            UPDATE webhook_deliveries d
+            SET attempts = d.attempts + 1,
+                next_attempt_at = now() + make_interval(secs => $2)
+            FROM webhook_subscriptions s
+            WHERE s.id = d.subscription_id
+              AND d.id IN (
+                  SELECT id FROM webhook_deliveries
+                  WHERE status = 'pending' AND next_attempt_at <= now()
+                  ORDER BY next_attempt_at
+                  LIMIT $1
+                  FOR UPDATE SKIP LOCKED
+              )
+            RETURNING d.id, d.event_id, d.event_type, d.payload, d.attempts, s.url, s.secret
+            "#
+        )
+        .bind(self.batch_size)
+        .bind(lease_secs)
+        .fetch_all(&self.pool)
+        .db_query("claim_webhook_deliveries")
+        .await?;
+
+        let count = due.len();
+        futures::future::join_all(due.into_iter().map(|delivery| self.deliver(delivery))).await;
+        Ok(count)
+    }
+
+    async fn deliver(&self, delivery: DueDelivery) {
+        let body = delivery.payload.to_string();
+        let attempt = send_webhook(
+            &self.client,
+            &delivery.url,
+            &delivery.secret,
+            delivery.event_id,
+            delivery.id,
+            &delivery.event_type,
+            &body,
+        )
+        .await;
+
+        let result = if attempt.succeeded() {
+            sqlx::query(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = 'succeeded', delivered_at = now(), last_status_code = $2, last_error = NULL
+                WHERE id = $1
+                "#
+            )
+            .bind(delivery.id)
+            .bind(attempt.status_code.map(i32::from))
+            .execute(&self.pool)
+            .db_query("record_webhook_delivery")
+            .await
+        } else {
+            let exhausted = delivery.attempts >= self.max_attempts;
+            let retry_in = backoff_secs(delivery.attempts, self.backoff_base_secs, self.backoff_max_secs) as f64;
+            tracing::warn!(
+                "Webhook delivery {} to {} failed (attempt {}): {}",
+                delivery.id,
+                delivery.url,
+                delivery.attempts,
+                attempt.error.as_deref().unwrap_or_default()
+            );
+            sqlx::query(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = CASE WHEN $2 THEN 'failed' ELSE 'pending' END,
+                    next_attempt_at = now() + make_interval(secs => $3),
+                    last_status_code = $4,
+                    last_error = $5
+                WHERE id = $1
+                "#
+            )
+            .bind(delivery.id)
+            .bind(exhausted)
+            .bind(retry_in)
+            .bind(attempt.status_code.map(i32::from))
+            .bind(&attempt.error)
+            .execute(&self.pool)
+            .db_query("record_webhook_delivery")
+            .await
+        };
+
+        if let Err(e) = result {
+            tracing::error!("Failed to record webhook delivery {}: {}", delivery.id, e);
+        }
+    }
+
+    pub fn spawn_worker(&self) {
+        if !self.enabled {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(service.poll_interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                loop {
+                    match service.dispatch_due().await {
+                        // A full batch suggests a backlog; keep draining.
+                        Ok(count) if count as i64 >= service.batch_size => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::warn!("Webhook dispatch failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// POSTs a signed event. Receivers should verify `X-Webhook-Signature`
+/// and deduplicate on `X-Webhook-Id`, which is the same for every retry.
+pub async fn send_webhook(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event_id: Uuid,
+    delivery_id: Uuid,
+    event_type: &str,
+    body: &str,
+) -> DeliveryAttempt {
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", event_id.to_string())
+        .header("X-Webhook-Delivery", delivery_id.to_string())
+        .header("X-Webhook-Event", event_type)
+        .header("X-Webhook-Signature", signature_header(secret, timestamp, body))
+        .body(body.to_string())
+        .send()
+        .await;
+
+    match response {
+        Ok(res) if res.status().is_success() => DeliveryAttempt {
+            status_code: Some(res.status().as_u16()),
+            error: None,
+        },
+        Ok(mut res) => {
+            let status = res.status();
+            let text = read_capped(&mut res, MAX_RESPONSE_BYTES).await;
+            DeliveryAttempt {
+                status_code: Some(status.as_u16()),
+                error: Some(truncate(&format!("HTTP {}: {}", status, text))),
+            }
+        }
+        Err(e) => DeliveryAttempt {
+            status_code: None,
+            error: Some(truncate(&e.to_string())),
+        },
+    }
+}
+
+/// Reads at most `limit` bytes of the body, so a subscriber cannot make the
+/// worker buffer an arbitrarily large error page.
+async fn read_capped(res: &mut reqwest::Response, limit: usize) -> String {
+    let mut body = Vec::new();
+    while body.len() < limit {
+        match res.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk[..chunk.len().min(limit - body.len())]),
+            _ => break,
+        }
+    }
+    String::from_utf8_lossy(&body).into_owned()
+}
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`. Including the
+/// timestamp lets receivers reject replays.
+pub fn signature_header(secret: &str, timestamp: i64, body: &str) -> String {
+    format!("t={},v1={}", timestamp, signature::sign(secret, &format!("{}.{}", timestamp, body)))
+}
+
+/// Delay before retrying after `attempts` failures: `base * 2^(attempts-1)`,
+/// capped at `max`.
+pub fn backoff_secs(attempts: i32, base: u64, max: u64) -> u64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 32) as u32;
+    base.saturating_mul(2u64.saturating_pow(exponent)).min(max)
+}
+
+fn truncate(message: &str) -> String {
+    message.chars().take(MAX_ERROR_LEN).collect()
+}
+
+/// Client for deliveries. Redirects are never followed and, unless private
+/// targets are allowed, host names are resolved with `PublicResolver`, so a
+/// subscriber cannot repoint its DNS at internal services after creation.
+pub fn http_client(timeout_secs: u64, allow_private_targets: bool) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .redirect(reqwest::redirect::Policy::none());
+    if !allow_private_targets {
+        builder = builder.dns_resolver(Arc::new(PublicResolver));
+    }
+    builder.build().expect("Failed to build webhook HTTP client")
+}
+
+/// Rejects URLs whose host is, or resolves to, a loopback, private or
+/// link-local address such as the cloud metadata endpoint.
+pub async fn check_public_target(url: &reqwest::Url) -> Result<(), AppError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::InvalidContent("Webhook URL must have a host".to_string()))?;
+    let literal = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>();
+    let result = match literal {
+        Ok(ip) if is_private_address(ip) => Err(format!("{} is not a public address", ip)),
+        Ok(_) => Ok(()),
+        Err(_) => public_addrs(host, url.port_or_known_default().unwrap_or(0)).await.map(|_| ()),
+    };
+    result.map_err(|e| AppError::InvalidContent(format!("Webhook URL not allowed: {}", e)))
+}
+
+/// Loopback, private, link-local, shared (CGNAT) and unspecified addresses,
+/// including their IPv4-mapped IPv6 forms.
+pub fn is_private_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback() || ip.is_unspecified() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+/// Resolves host names for deliveries, failing when any address is private.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = public_addrs(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+async fn public_addrs(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("could not resolve {}: {}", host, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("{} did not resolve", host));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| is_private_address(addr.ip())) {
+        return Err(format!("{} resolves to {}, which is not a public address", host, addr.ip()));
+    }
+    Ok(addrs)
+}
\ No newline at end of file
@@ -0,0 +1,104 @@
+use crate::error::AppError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// Coalesces concurrent loads of the same key: the first caller runs the
+/// load, later callers wait for its result, errors included. Waiting
+/// callers only run the load themselves if the leader is cancelled or fails
+/// with an error that cannot be shared (see [`AppError::try_clone`]).
+pub struct SingleFlight<V> {
+    inflight: Mutex<HashMap<String, watch::Receiver<Outcome<V>>>>,
+}
+
+impl<V: Clone> Default for SingleFlight<V> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<V: Clone> SingleFlight<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn run<F, Fut>(&self, key: &str, load: F) -> Result<V, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, AppError>>,
+    {
+        let role = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(key) {
+                Some(rx) => Role::Follower(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    inflight.insert(key.to_string(), rx);
+                    Role::Leader(tx)
+                }
+            }
+        };
+
+        match role {
+            Role::Leader(tx) => self.lead(key, tx, load).await,
+            Role::Follower(mut rx) => {
+                let shared = match rx.wait_for(|v| v.is_some()).await {
+                    Ok(outcome) => match outcome.as_ref() {
+                        Some(Ok(value)) => Some(Ok(value.clone())),
+                        Some(Err(e)) => e.try_clone().map(Err),
+                        None => None,
+                    },
+                    Err(_) => None,
+                };
+                match shared {
+                    Some(result) => result,
+                    None => load().await,
+                }
+            }
+        }
+    }
+
+    async fn lead<F, Fut>(
+        &self,
+        key: &str,
+        tx: watch::Sender<Outcome<V>>,
+        load: F,
+    ) -> Result<V, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, AppError>>,
+    {
+        let _guard = InflightGuard { flight: self, key };
+        let result = load().await;
+        let shared = match &result {
+            Ok(value) => Some(Ok(value.clone())),
+            Err(e) => e.try_clone().map(Err),
+        };
+        if let Some(shared) = shared {
+            let _ = tx.send(Some(shared));
+        }
+        result
+    }
+}
+
+/// `None` until the leader finishes.
+type Outcome<V> = Option<Result<V, AppError>>;
+
+enum Role<V> {
+    Leader(watch::Sender<Outcome<V>>),
+    Follower(watch::Receiver<Outcome<V>>),
+}
+
+struct InflightGuard<'a, V> {
+    flight: &'a SingleFlight<V>,
+    key: &'a str,
+}
+
+impl<V> Drop for InflightGuard<'_, V> {
+    fn drop(&mut self) {
+        self.flight.inflight.lock().unwrap().remove(self.key);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::Config;
+use crate::middleware::metrics::DB_QUERIES_TOTAL;
+use actix_web::http::header::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::future::Future;
+use tracing::{Instrument, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global subscriber: JSON logs, plus OTLP trace export when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set. The returned provider must be
+/// shut down on exit to flush buffered spans.
+pub fn init(config: &Config) -> Option<SdkTracerProvider> {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let (provider, export_error) = match config
+        .otel_exporter_endpoint
+        .as_deref()
+        .map(|endpoint| tracer_provider(endpoint, &config.otel_service_name, config.otel_sampling_ratio))
+        .transpose()
+    {
+        Ok(provider) => (provider, None),
+        Err(e) => (None, Some(e)),
+    };
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("templates-service"))
+    });
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(tracing_subscriber::fmt::layer().json())
+        .with(otel_layer)
+        .init();
+
+    // Reported only now so the warning goes through the JSON log.
+    if let Some(e) = export_error {
+        tracing::warn!("OTLP trace export disabled: {}", e);
+    }
+
+    provider
+}
+
+/// Batch-exports spans over OTLP/HTTP to `{endpoint}/v1/traces`. Sampling
+/// follows the caller's `traceparent` decision when there is one.
+pub fn tracer_provider(
+    endpoint: &str,
+    service_name: &str,
+    sampling_ratio: f64,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_endpoint(endpoint))
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sampling_ratio))))
+        .with_resource(Resource::builder().with_service_name(service_name.to_string()).build())
+        .build())
+}
+
+/// OTLP/HTTP traces URL for a collector base URL, as the
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` convention defines it.
+pub fn traces_endpoint(endpoint: &str) -> String {
+    format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+}
+
+pub fn db_span(operation: &str) -> Span {
+    tracing::info_span!(
+        "db.query",
+        otel.name = %format!("postgres {}", operation),
+        otel.kind = "client",
+        db.system = "postgresql",
+        db.operation.name = %operation,
+    )
+}
+
+/// Runs database queries in a `db_span` and, once they complete, counts
+/// them per operation and outcome.
+pub trait DbQuery<T, E>: Future<Output = Result<T, E>> + Sized {
+    fn db_query(self, operation: &'static str) -> impl Future<Output = Result<T, E>> {
+        async move {
+            let result = self.instrument(db_span(operation)).await;
+            let status = if result.is_ok() { "ok" } else { "error" };
+            DB_QUERIES_TOTAL.with_label_values(&[operation, status]).inc();
+            result
+        }
+    }
+}
+
+impl<F, T, E> DbQuery<T, E> for F where F: Future<Output = Result<T, E>> {}
+
+pub fn redis_span(command: &str) -> Span {
+    tracing::info_span!(
+        "redis.command",
+        otel.name = %format!("redis {}", command),
+        otel.kind = "client",
+        db.system = "redis",
+        db.operation.name = %command,
+    )
+}
+
+/// Reads W3C trace context from actix request headers.
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
\ No newline at end of file
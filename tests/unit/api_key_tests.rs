@@ -0,0 +1,144 @@
+//! The `#[ignore]`d cases need a migrated Postgres at `DATABASE_URL`:
+//! `DATABASE_URL=postgres://localhost/templates_db cargo test -- --ignored`.
+
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+use templates_service::error::AppError;
+use templates_service::models::CreateApiKeyRequest;
+use templates_service::services::api_key_service::{generate_key, hash_key, last_used_is_stale};
+use templates_service::services::ApiKeyService;
+use uuid::Uuid;
+
+use super::support::db_pool;
+
+fn create_request(scopes: &[&str]) -> CreateApiKeyRequest {
+    CreateApiKeyRequest {
+        name: format!("test-{}", Uuid::new_v4()),
+        scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        tenant: None,
+        expires_at: None,
+    }
+}
+
+#[test]
+fn test_generated_keys_carry_256_random_bits() {
+    let keys: HashSet<String> = (0..100).map(|_| generate_key()).collect();
+
+    assert_eq!(keys.len(), 100);
+    for key in &keys {
+        let secret = key.strip_prefix("tsk_").unwrap();
+        assert_eq!(secret.len(), 64);
+        assert!(secret.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
+
+#[test]
+fn test_keys_are_stored_as_sha256_hex() {
+    assert_eq!(hash_key("tsk_abc"), "c4a98b7b973a42b43da3dcb611c9f10a82de037aeacde8a1d7bb48472276c9a7");
+    assert_ne!(hash_key("tsk_abc"), hash_key("tsk_abd"));
+}
+
+#[test]
+fn test_last_used_is_written_at_most_once_a_minute() {
+    let now = Utc::now();
+
+    assert!(last_used_is_stale(None, now));
+    assert!(!last_used_is_stale(Some(now - Duration::seconds(30)), now));
+    assert!(last_used_is_stale(Some(now - Duration::seconds(61)), now));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_create_list_and_revoke() {
+    let pool = db_pool().await;
+    let service = ApiKeyService::new(pool.clone());
+
+    let (created, plaintext) = service.create_key(create_request(&["templates:read"]), None).await.unwrap();
+    let stored_hash: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE id = $1")
+        .bind(created.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored_hash, hash_key(&plaintext));
+    assert!(plaintext.starts_with(&created.key_prefix));
+    assert!(service.list_keys(None).await.unwrap().iter().any(|k| k.id == created.id));
+
+    service.revoke_key(created.id, None).await.unwrap();
+    assert!(service.authenticate(&plaintext).await.unwrap().is_none());
+    assert!(matches!(service.revoke_key(created.id, None).await, Err(AppError::ApiKeyNotFound)));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_create_rejects_unknown_and_missing_scopes() {
+    let service = ApiKeyService::new(db_pool().await);
+
+    assert!(matches!(service.create_key(create_request(&[]), None).await, Err(AppError::InvalidScope(_))));
+    assert!(matches!(
+        service.create_key(create_request(&["templates:everything"]), None).await,
+        Err(AppError::InvalidScope(_))
+    ));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_authenticate_looks_up_by_hash_and_refreshes_last_used() {
+    let service = ApiKeyService::new(db_pool().await);
+    let (created, plaintext) = service.create_key(create_request(&["templates:read"]), None).await.unwrap();
+
+    let first = service.authenticate(&plaintext).await.unwrap().unwrap();
+    assert_eq!(first.id, created.id);
+    assert!(first.last_used_at.is_none());
+
+    let second = service.authenticate(&plaintext).await.unwrap().unwrap();
+    let last_used = second.last_used_at.unwrap();
+    let third = service.authenticate(&plaintext).await.unwrap().unwrap();
+    assert_eq!(third.last_used_at, Some(last_used));
+
+    assert!(service.authenticate(&hash_key(&plaintext)).await.unwrap().is_none());
+    assert!(service.authenticate(&format!("{}0", plaintext)).await.unwrap().is_none());
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_expired_keys_are_rejected() {
+    let service = ApiKeyService::new(db_pool().await);
+    let request = CreateApiKeyRequest {
+        expires_at: Some(Utc::now() - Duration::seconds(1)),
+        ..create_request(&["templates:read"])
+    };
+    let (_, plaintext) = service.create_key(request, None).await.unwrap();
+
+    assert!(service.authenticate(&plaintext).await.unwrap().is_none());
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_tenant_bound_callers_only_manage_their_own_keys() {
+    let service = ApiKeyService::new(db_pool().await);
+    let acme = format!("test-acme-{}", Uuid::new_v4());
+    let globex = format!("test-globex-{}", Uuid::new_v4());
+
+    let (own, _) = service.create_key(create_request(&["templates:read"]), Some(&acme)).await.unwrap();
+    assert_eq!(own.tenant.as_deref(), Some(acme.as_str()));
+
+    let other_request = CreateApiKeyRequest {
+        tenant: Some(globex.clone()),
+        ..create_request(&["templates:read"])
+    };
+    assert!(matches!(
+        service.create_key(other_request, Some(&acme)).await,
+        Err(AppError::Forbidden(_))
+    ));
+    let other_request = CreateApiKeyRequest {
+        tenant: Some(globex.clone()),
+        ..create_request(&["templates:read"])
+    };
+    let (other, _) = service.create_key(other_request, None).await.unwrap();
+
+    let listed: Vec<Uuid> = service.list_keys(Some(&acme)).await.unwrap().iter().map(|k| k.id).collect();
+    assert_eq!(listed, vec![own.id]);
+
+    assert!(matches!(service.revoke_key(other.id, Some(&acme)).await, Err(AppError::ApiKeyNotFound)));
+    service.revoke_key(other.id, Some(&globex)).await.unwrap();
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+//! The `#[ignore]`d case needs Redis at `REDIS_URL` and a migrated Postgres
+//! at `DATABASE_URL`: `cargo test -- --ignored`.
+
+use actix_web::http::StatusCode;
+use actix_web::test::{call_and_read_body_json, call_service, init_service, TestRequest};
+use actix_web::{web, App};
+use serde_json::{json, Value};
+use serial_test::serial;
+use std::collections::HashMap;
+use templates_service::cache::RedisPool;
+use templates_service::db::DbPool;
+use templates_service::handlers::{flush_cache, inspect_template_cache, purge_template_cache};
+use templates_service::models::CreateTemplateRequest;
+use templates_service::services::{RenderService, TemplateService};
+use uuid::Uuid;
+
+use super::support::{config, db_pool, offline_db_pool, offline_redis_pool, redis_pool};
+
+fn services(pool: DbPool, redis: RedisPool) -> (TemplateService, RenderService) {
+    (
+        TemplateService::new(pool, redis.clone(), &config(&[])),
+        RenderService::new(redis, config(&[])),
+    )
+}
+
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/cache", web::delete().to(flush_cache))
+        .route("/cache/templates/{template_code}", web::get().to(inspect_template_cache))
+        .route("/cache/templates/{template_code}", web::delete().to(purge_template_cache));
+}
+
+#[actix_rt::test]
+async fn test_flush_reports_unavailable_redis() {
+    let (templates, renderer) = services(offline_db_pool(), offline_redis_pool().await);
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(templates))
+            .app_data(web::Data::new(renderer))
+            .configure(routes),
+    )
+    .await;
+
+    let res = call_service(&app, TestRequest::delete().uri("/cache").to_request()).await;
+
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis and a migrated Postgres"]
+async fn test_inspect_purge_and_flush() {
+    let (templates, renderer) = services(db_pool().await, redis_pool().await);
+    let code = format!("cache-{}", Uuid::new_v4());
+    let template = templates
+        .create_template(CreateTemplateRequest {
+            template_code: code.clone(),
+            template_type: "email_html".to_string(),
+            language: "en".to_string(),
+            content: "<p>Hi {{ name }}</p>".to_string(),
+            meta: None,
+        })
+        .await
+        .unwrap();
+    let variables = HashMap::from([("name".to_string(), json!("Ada"))]);
+    renderer.render(&template, &variables).await.unwrap();
+    templates.get_template(&code, Some("en"), None).await.unwrap();
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(templates))
+            .app_data(web::Data::new(renderer))
+            .configure(routes),
+    )
+    .await;
+    let uri = format!("/cache/templates/{}", code);
+
+    let state: Value = call_and_read_body_json(&app, TestRequest::get().uri(&uri).to_request()).await;
+    let version = &state["data"]["versions"][0];
+    assert_eq!(version["compiled"], true);
+    assert_eq!(version["rendered_keys"].as_array().unwrap().len(), 1);
+    assert_eq!(state["data"]["latest_keys"].as_array().unwrap().len(), 1);
+
+    let purged: Value = call_and_read_body_json(&app, TestRequest::delete().uri(&uri).to_request()).await;
+    assert_eq!(purged["data"]["purged_versions"], 1);
+
+    let state: Value = call_and_read_body_json(&app, TestRequest::get().uri(&uri).to_request()).await;
+    let version = &state["data"]["versions"][0];
+    assert_eq!(version["compiled"], false);
+    assert_eq!(version["rendered_keys"].as_array().unwrap().len(), 0);
+    assert_eq!(version["template_key"], Value::Null);
+
+    let flushed: Value = call_and_read_body_json(&app, TestRequest::delete().uri("/cache").to_request()).await;
+    assert!(flushed["message"].as_str().unwrap().contains("this instance only"));
+
+    let missing = TestRequest::get().uri("/cache/templates/no-such-template").to_request();
+    assert_eq!(call_service(&app, missing).await.status(), StatusCode::NOT_FOUND);
+}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use redis::cluster_routing::get_slot;
+use templates_service::cache::{rendered_index_key, rendered_key, template_key};
+
+#[test]
+fn test_template_keys() {
+    assert_eq!(template_key("welcome", Some(3), "en"), "template:{welcome}:3:en");
+    assert_eq!(template_key("welcome", None, "en"), "template:{welcome}:latest:en");
+}
+
+#[test]
+fn test_rendered_keys_share_index_scope() {
+    let key = rendered_key("welcome", 3, "en", "abc123");
+    let index = rendered_index_key("welcome", 3, "en");
+
+    assert_eq!(key, "rendered:{welcome}:3:en:abc123");
+    assert_eq!(index, "rendered_index:{welcome}:3:en");
+}
+
+#[test]
+fn test_template_keys_share_cluster_slot() {
+    let slot = get_slot(template_key("welcome", None, "en").as_bytes());
+
+    for key in [
+        template_key("welcome", Some(3), "fr"),
+        rendered_key("welcome", 3, "en", "abc123"),
+        rendered_index_key("welcome", 7, "de"),
+    ] {
+        assert_eq!(get_slot(key.as_bytes()), slot, "{} is in another slot", key);
+    }
+
+    assert_ne!(get_slot(template_key("goodbye", None, "en").as_bytes()), slot);
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+use templates_service::cache::{encode_entry, is_sensitive, parse_cache_overrides, CachePolicy};
+
+const DEFAULTS: CachePolicy = CachePolicy {
+    template_ttl_secs: 3600,
+    rendered_ttl_secs: 300,
+};
+
+#[test]
+fn test_defaults_apply_without_overrides() {
+    assert_eq!(DEFAULTS.for_meta(None), DEFAULTS);
+    assert_eq!(DEFAULTS.for_meta(Some(&json!({ "owner": "growth" }))), DEFAULTS);
+}
+
+#[test]
+fn test_meta_overrides_ttls() {
+    let meta = json!({ "cache": { "template_ttl_secs": 60, "rendered_ttl_secs": 0 } });
+    let policy = DEFAULTS.for_meta(Some(&meta));
+
+    assert_eq!(policy.template_ttl_secs, 60);
+    assert_eq!(policy.rendered_ttl_secs, 0);
+}
+
+#[test]
+fn test_partial_override_keeps_other_default() {
+    let meta = json!({ "cache": { "rendered_ttl_secs": 30 } });
+    let policy = DEFAULTS.for_meta(Some(&meta));
+
+    assert_eq!(policy.template_ttl_secs, 3600);
+    assert_eq!(policy.rendered_ttl_secs, 30);
+}
+
+#[test]
+fn test_malformed_overrides_are_rejected() {
+    assert!(parse_cache_overrides(Some(&json!({ "cache": { "rendered_ttl_secs": -1 } }))).is_err());
+    assert!(parse_cache_overrides(Some(&json!({ "cache": { "ttl": 10 } }))).is_err());
+    assert!(parse_cache_overrides(Some(&json!({ "cache": "off" }))).is_err());
+}
+
+#[test]
+fn test_zero_ttl_is_never_encoded() {
+    assert!(encode_entry(&"otp 123456", 0, 60).is_none());
+    assert_eq!(encode_entry(&"hello", 10, 5).map(|(_, ttl)| ttl), Some(15));
+}
+
+#[test]
+fn test_sensitive_templates_never_cache_output() {
+    let meta = json!({ "sensitive": true, "cache": { "rendered_ttl_secs": 600, "template_ttl_secs": 60 } });
+    let policy = DEFAULTS.for_meta(Some(&meta));
+
+    assert_eq!(policy.rendered_ttl_secs, 0);
+    assert_eq!(policy.template_ttl_secs, 60);
+    assert!(is_sensitive(Some(&json!({ "sensitive": "yes" }))).is_err());
+    assert_eq!(is_sensitive(Some(&json!({ "sensitive": false }))), Ok(false));
+}
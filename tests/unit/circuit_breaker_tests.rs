@@ -0,0 +1,55 @@
+use std::time::Duration;
+use templates_service::circuit_breaker::CircuitBreaker;
+
+#[test]
+fn test_opens_after_threshold_consecutive_failures() {
+    let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+    breaker.record_failure();
+    breaker.record_failure();
+    assert!(breaker.allow());
+
+    breaker.record_failure();
+    assert!(breaker.is_open());
+    assert!(!breaker.allow());
+}
+
+#[test]
+fn test_success_resets_failure_count() {
+    let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+    breaker.record_failure();
+    breaker.record_success();
+    breaker.record_failure();
+
+    assert!(!breaker.is_open());
+    assert!(breaker.allow());
+}
+
+#[test]
+fn test_half_open_allows_single_probe() {
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+    breaker.record_failure();
+    assert!(!breaker.allow());
+
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(breaker.allow());
+    assert!(!breaker.allow());
+
+    breaker.record_success();
+    assert!(!breaker.is_open());
+    assert!(breaker.allow());
+}
+
+#[test]
+fn test_failed_probe_reopens_circuit() {
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+    breaker.record_failure();
+
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(breaker.allow());
+    breaker.record_failure();
+
+    assert!(breaker.is_open());
+    assert!(!breaker.allow());
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use templates_service::services::CompiledCache;
+use tera::Tera;
+
+fn compiled(content: &str) -> Arc<Tera> {
+    let mut tera = Tera::default();
+    tera.add_raw_template("t", content).unwrap();
+    Arc::new(tera)
+}
+
+#[test]
+fn test_evicts_least_recently_used_by_count() {
+    let cache = CompiledCache::new(2, usize::MAX);
+    cache.insert("a".to_string(), compiled("a"), 1);
+    cache.insert("b".to_string(), compiled("b"), 1);
+    assert!(cache.get("a").is_some());
+
+    cache.insert("c".to_string(), compiled("c"), 1);
+
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("a").is_some());
+    assert!(cache.contains("c"));
+}
+
+#[test]
+fn test_evicts_to_stay_within_byte_budget() {
+    let cache = CompiledCache::new(100, 10);
+    cache.insert("a".to_string(), compiled("a"), 6);
+    cache.insert("b".to_string(), compiled("b"), 6);
+
+    assert!(!cache.contains("a"));
+    assert!(cache.get("b").is_some());
+}
+
+#[test]
+fn test_shared_compiled_template_renders() {
+    let cache = CompiledCache::new(10, usize::MAX);
+    cache.insert("hello".to_string(), compiled("Hello {{ name }}"), 16);
+
+    let tera = cache.get("hello").unwrap();
+    let mut context = tera::Context::new();
+    context.insert("name", "World");
+    assert_eq!(tera.render("t", &context).unwrap(), "Hello World");
+}
+
+#[test]
+fn test_remove_and_clear() {
+    let cache = CompiledCache::new(10, usize::MAX);
+    cache.insert("a".to_string(), compiled("a"), 1);
+    cache.insert("b".to_string(), compiled("b"), 1);
+    cache.insert("c".to_string(), compiled("c"), 1);
+
+    assert!(cache.remove("a"));
+    assert!(!cache.remove("a"));
+    assert!(!cache.contains("a"));
+    assert!(cache.contains("b"));
+
+    assert_eq!(cache.clear(), 2);
+    assert_eq!(cache.clear(), 0);
+}
+
+#[test]
+fn test_replacing_an_entry_releases_its_bytes() {
+    let cache = CompiledCache::new(100, 10);
+    cache.insert("a".to_string(), compiled("a"), 6);
+    cache.insert("a".to_string(), compiled("a2"), 6);
+    cache.insert("b".to_string(), compiled("b"), 4);
+
+    assert!(cache.contains("a"));
+    assert!(cache.contains("b"));
+
+    assert!(cache.remove("a"));
+    cache.insert("c".to_string(), compiled("c"), 6);
+    assert!(cache.contains("b"));
+    assert!(cache.contains("c"));
+}
\ No newline at end of file
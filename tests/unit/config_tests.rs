@@ -0,0 +1,13 @@
+use super::support::config;
+
+#[test]
+fn test_hmac_scopes_are_parsed() {
+    let config = config(&[("HMAC_SCOPES", "templates:read, ops")]);
+    assert_eq!(config.hmac_scopes, vec!["templates:read", "ops"]);
+}
+
+#[test]
+#[should_panic(expected = "HMAC_SCOPES must not include admin")]
+fn test_hmac_scopes_reject_admin() {
+    config(&[("HMAC_SCOPES", "templates:read,admin")]);
+}
\ No newline at end of file
@@ -0,0 +1,36 @@
+use templates_service::crypto::PayloadCipher;
+
+#[test]
+fn test_round_trips_under_same_key() {
+    let cipher = PayloadCipher::from_secret("secret");
+    let sealed = cipher.encrypt("rendered:otp:1:en:abc", "{\"code\":\"123456\"}");
+
+    assert!(sealed.starts_with("enc1:"));
+    assert!(!sealed.contains("123456"));
+    assert_eq!(
+        cipher.decrypt("rendered:otp:1:en:abc", &sealed).as_deref(),
+        Some("{\"code\":\"123456\"}")
+    );
+}
+
+#[test]
+fn test_nonces_differ_between_encryptions() {
+    let cipher = PayloadCipher::from_secret("secret");
+    assert_ne!(cipher.encrypt("k", "same"), cipher.encrypt("k", "same"));
+}
+
+#[test]
+fn test_rejects_other_cache_key_or_secret() {
+    let sealed = PayloadCipher::from_secret("secret").encrypt("key-a", "payload");
+
+    assert!(PayloadCipher::from_secret("secret").decrypt("key-b", &sealed).is_none());
+    assert!(PayloadCipher::from_secret("other").decrypt("key-a", &sealed).is_none());
+}
+
+#[test]
+fn test_plaintext_entries_are_not_accepted() {
+    let cipher = PayloadCipher::from_secret("secret");
+    assert!(cipher.decrypt("k", "{\"fresh_until\":0,\"value\":1}").is_none());
+    assert!(cipher.decrypt("k", "enc1:zz").is_none());
+    assert!(cipher.decrypt("k", "enc1:00").is_none());
+}
@@ -0,0 +1,93 @@
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use serde_json::Value;
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+use templates_service::error::AppError;
+
+#[derive(Debug)]
+struct UniqueViolation;
+
+impl fmt::Display for UniqueViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl StdError for UniqueViolation {}
+
+impl DatabaseError for UniqueViolation {
+    fn message(&self) -> &str {
+        "duplicate key value violates unique constraint \"templates_template_code_version_language_key\""
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed("23505"))
+    }
+
+    fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::UniqueViolation
+    }
+}
+
+async fn body_of(err: &AppError) -> Value {
+    let body = actix_web::body::to_bytes(err.error_response().into_body()).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[actix_rt::test]
+async fn test_unique_violation_is_conflict() {
+    let err = AppError::DatabaseError(sqlx::Error::Database(Box::new(UniqueViolation)));
+    assert_eq!(err.status_code(), StatusCode::CONFLICT);
+
+    let body = body_of(&err).await;
+    assert_eq!(body["error"], "conflict");
+    assert!(!body["message"].as_str().unwrap().contains("templates_template_code"));
+}
+
+#[actix_rt::test]
+async fn test_pool_timeout_is_unavailable() {
+    let err = AppError::DatabaseError(sqlx::Error::PoolTimedOut);
+    assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body_of(&err).await["error"], "db_unavailable");
+}
+
+#[actix_rt::test]
+async fn test_internal_details_are_not_returned() {
+    let err = AppError::DatabaseError(sqlx::Error::Protocol("unexpected message 'Z' from 10.0.0.5".to_string()));
+    assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = body_of(&err).await;
+    assert_eq!(body["error"], "db_error");
+    assert_eq!(body["message"], "A database error occurred");
+    assert!(err.to_string().contains("10.0.0.5"));
+
+    let err = AppError::InternalError("Serialize error: key must be a string".to_string());
+    assert_eq!(body_of(&err).await["message"], "Internal server error");
+
+    let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "redis-0.internal:6379");
+    let err = AppError::RedisError(redis::RedisError::from(refused));
+    assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = body_of(&err).await;
+    assert_eq!(body["error"], "cache_unavailable");
+    assert!(!body["message"].as_str().unwrap().contains("redis-0.internal"));
+}
+
+#[actix_rt::test]
+async fn test_client_errors_keep_their_message() {
+    let err = AppError::InvalidContent("Invalid JSON: expected value".to_string());
+    assert_eq!(body_of(&err).await["message"], "Invalid content: Invalid JSON: expected value");
+}
\ No newline at end of file
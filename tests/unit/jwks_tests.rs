@@ -0,0 +1,123 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use std::sync::Arc;
+use templates_service::jwks::JwksStore;
+use templates_service::middleware::auth::Principal;
+use templates_service::middleware::JwtVerifier;
+
+use super::support::spawn_http_stub;
+
+const JWKS: &str = include_str!("../fixtures/jwks/jwks.json");
+const RSA_PRIVATE: &str = include_str!("../fixtures/jwks/rsa_private.pem");
+const EC_PRIVATE: &str = include_str!("../fixtures/jwks/ec_private.pem");
+
+fn serve_jwks() -> String {
+    let (base_url, _requests) = spawn_http_stub("200 OK", JWKS);
+    format!("{}/.well-known/jwks.json", base_url)
+}
+
+fn token(alg: Algorithm, kid: &str, key: &EncodingKey, claims: serde_json::Value) -> String {
+    let mut header = Header::new(alg);
+    header.kid = Some(kid.to_string());
+    encode(&header, &claims, key).unwrap()
+}
+
+fn claims(iss: &str, aud: &str) -> serde_json::Value {
+    serde_json::json!({
+        "sub": "user-1",
+        "exp": chrono::Utc::now().timestamp() + 600,
+        "type": "access",
+        "iss": iss,
+        "aud": aud,
+    })
+}
+
+#[actix_rt::test]
+async fn test_rs256_token_verified_with_jwks_from_url() {
+    let store = Arc::new(JwksStore::new(serve_jwks()));
+    assert_eq!(store.refresh().await.unwrap(), 2);
+
+    let verifier = JwtVerifier::new(
+        None,
+        Some(store),
+        Some("https://issuer.test".to_string()),
+        vec!["templates".to_string()],
+    );
+
+    let key = EncodingKey::from_rsa_pem(RSA_PRIVATE.as_bytes()).unwrap();
+    let jwt = token(Algorithm::RS256, "rsa-test-key", &key, claims("https://issuer.test", "templates"));
+
+    let verified = verifier.verify(&jwt).unwrap();
+    assert_eq!(verified.sub, "user-1");
+}
+
+#[actix_rt::test]
+async fn test_es256_token_verified_with_jwks_from_file() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwks/jwks.json");
+    let store = Arc::new(JwksStore::new(path));
+    store.refresh().await.unwrap();
+
+    let verifier = JwtVerifier::new(None, Some(store), None, vec![]);
+    let key = EncodingKey::from_ec_pem(EC_PRIVATE.as_bytes()).unwrap();
+    let jwt = token(Algorithm::ES256, "ec-test-key", &key, claims("any", "any"));
+
+    assert!(verifier.verify(&jwt).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_rejects_unknown_kid_issuer_and_audience() {
+    let store = Arc::new(JwksStore::new(serve_jwks()));
+    store.refresh().await.unwrap();
+
+    let verifier = JwtVerifier::new(
+        None,
+        Some(store),
+        Some("https://issuer.test".to_string()),
+        vec!["templates".to_string()],
+    );
+    let key = EncodingKey::from_rsa_pem(RSA_PRIVATE.as_bytes()).unwrap();
+
+    let unknown_kid = token(Algorithm::RS256, "rotated-away", &key, claims("https://issuer.test", "templates"));
+    assert_eq!(verifier.verify(&unknown_kid).unwrap_err(), "Unknown signing key");
+
+    let wrong_issuer = token(Algorithm::RS256, "rsa-test-key", &key, claims("https://evil.test", "templates"));
+    assert!(verifier.verify(&wrong_issuer).is_err());
+
+    let wrong_audience = token(Algorithm::RS256, "rsa-test-key", &key, claims("https://issuer.test", "billing"));
+    assert!(verifier.verify(&wrong_audience).is_err());
+
+    let mut no_audience = claims("https://issuer.test", "templates");
+    no_audience.as_object_mut().unwrap().remove("aud");
+    let missing_audience = token(Algorithm::RS256, "rsa-test-key", &key, no_audience);
+    assert!(verifier.verify(&missing_audience).is_err());
+
+    let mut no_issuer = claims("https://issuer.test", "templates");
+    no_issuer.as_object_mut().unwrap().remove("iss");
+    let missing_issuer = token(Algorithm::RS256, "rsa-test-key", &key, no_issuer);
+    assert!(verifier.verify(&missing_issuer).is_err());
+}
+
+#[test]
+fn test_hs256_requires_configured_secret() {
+    let claims = claims("any", "any");
+    let jwt = encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+
+    let with_secret = JwtVerifier::new(Some("secret"), None, None, vec![]);
+    assert!(with_secret.verify(&jwt).is_ok());
+
+    let without_secret = JwtVerifier::new(None, None, None, vec![]);
+    assert_eq!(without_secret.verify(&jwt).unwrap_err(), "Unsupported token algorithm");
+}
+
+#[test]
+fn test_tenant_claim_binds_the_principal() {
+    let mut claims = claims("any", "any");
+    let secret = EncodingKey::from_secret(b"secret");
+    let verifier = JwtVerifier::new(Some("secret"), None, None, vec![]);
+
+    let jwt = encode(&Header::new(Algorithm::HS256), &claims, &secret).unwrap();
+    assert_eq!(Principal::from_claims(&verifier.verify(&jwt).unwrap()).tenant, None);
+
+    claims["tenant"] = "acme".into();
+    let jwt = encode(&Header::new(Algorithm::HS256), &claims, &secret).unwrap();
+    assert_eq!(Principal::from_claims(&verifier.verify(&jwt).unwrap()).tenant.as_deref(), Some("acme"));
+}
\ No newline at end of file
@@ -1,2 +1,28 @@
+mod support;
+
 mod template_validation_tests;
-mod render_tests;
\ No newline at end of file
+mod render_tests;
+mod jwks_tests;
+mod signature_tests;
+mod rate_limit_tests;
+mod cache_key_tests;
+mod local_cache_tests;
+mod compiled_cache_tests;
+mod singleflight_tests;
+mod circuit_breaker_tests;
+mod cache_policy_tests;
+mod crypto_tests;
+mod redis_topology_tests;
+mod openapi_tests;
+mod metrics_tests;
+mod usage_tests;
+mod telemetry_tests;
+mod request_id_tests;
+mod error_tests;
+mod webhook_tests;
+mod outbox_tests;
+mod template_warm_tests;
+mod api_key_tests;
+mod route_auth_tests;
+mod cache_handler_tests;
+mod config_tests;
\ No newline at end of file
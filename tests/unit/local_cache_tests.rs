@@ -0,0 +1,34 @@
+use std::time::Duration;
+use templates_service::local_cache::LocalCache;
+
+#[test]
+fn test_evicts_least_recently_used() {
+    let cache = LocalCache::new(2, Duration::from_secs(60));
+    cache.insert("a".to_string(), 1);
+    cache.insert("b".to_string(), 2);
+    assert_eq!(cache.get("a"), Some(1));
+
+    cache.insert("c".to_string(), 3);
+
+    assert_eq!(cache.get("b"), None);
+    assert_eq!(cache.get("a"), Some(1));
+    assert_eq!(cache.get("c"), Some(3));
+}
+
+#[test]
+fn test_entries_expire_after_ttl() {
+    let cache = LocalCache::new(10, Duration::from_millis(20));
+    cache.insert("a".to_string(), 1);
+    assert_eq!(cache.get("a"), Some(1));
+
+    std::thread::sleep(Duration::from_millis(30));
+
+    assert_eq!(cache.get("a"), None);
+}
+
+#[test]
+fn test_zero_ttl_disables_cache() {
+    let cache = LocalCache::new(10, Duration::ZERO);
+    cache.insert("a".to_string(), 1);
+    assert_eq!(cache.get("a"), None);
+}
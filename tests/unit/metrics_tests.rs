@@ -0,0 +1,131 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::{error, test, web, App, HttpResponse};
+use serde_json::json;
+use serial_test::serial;
+use std::collections::HashMap;
+use std::future::ready;
+use templates_service::error::AppError;
+use templates_service::middleware::metrics::{
+    DB_QUERIES_TOTAL, HTTP_REQUESTS_IN_FLIGHT, HTTP_REQUESTS_TOTAL, HTTP_RESPONSE_SIZE,
+    TEMPLATE_COMPILE_DURATION, TEMPLATE_RENDER_DURATION, TEMPLATE_RENDER_ERRORS, UNMATCHED_ROUTE,
+};
+use templates_service::middleware::Metrics;
+use templates_service::models::Template;
+use templates_service::services::RenderService;
+use templates_service::telemetry::DbQuery;
+
+use super::support::{config, offline_redis_pool, template};
+
+#[actix_rt::test]
+#[serial]
+async fn test_labels_by_route_pattern_and_unmatched_bucket() {
+    let app = test::init_service(
+        App::new().wrap(Metrics).route(
+            "/metrics-test/{code}",
+            web::get().to(|| async { HttpResponse::Ok().body("hello") }),
+        ),
+    )
+    .await;
+
+    for path in ["/metrics-test/welcome", "/metrics-test/goodbye", "/metrics-test-missing/x"] {
+        test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+    }
+
+    let matched = HTTP_REQUESTS_TOTAL.with_label_values(&["GET", "/metrics-test/{code}", "200"]);
+    assert_eq!(matched.get(), 2.0);
+    assert!(HTTP_REQUESTS_TOTAL.with_label_values(&["GET", UNMATCHED_ROUTE, "404"]).get() >= 1.0);
+
+    let sizes = HTTP_RESPONSE_SIZE.with_label_values(&["GET", "/metrics-test/{code}"]);
+    assert_eq!(sizes.get_sample_count(), 2);
+    assert_eq!(sizes.get_sample_sum(), 10.0);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_counts_errors_returned_by_inner_middleware() {
+    let app = test::init_service(
+        App::new().wrap(Metrics).service(
+            web::resource("/metrics-test-denied")
+                .wrap_fn(|_req, _srv| ready(Err::<ServiceResponse, _>(error::ErrorForbidden("denied"))))
+                .route(web::get().to(HttpResponse::Ok)),
+        ),
+    )
+    .await;
+
+    let denied = HTTP_REQUESTS_TOTAL.with_label_values(&["GET", "/metrics-test-denied", "403"]);
+    let res = test::try_call_service(&app, test::TestRequest::get().uri("/metrics-test-denied").to_request()).await;
+
+    assert!(res.is_err());
+    assert_eq!(denied.get(), 1.0);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_in_flight_gauge_covers_the_request() {
+    let app = test::init_service(App::new().wrap(Metrics).route(
+        "/metrics-test-in-flight",
+        web::get().to(|| async { HttpResponse::Ok().body(HTTP_REQUESTS_IN_FLIGHT.get().to_string()) }),
+    ))
+    .await;
+
+    let before = HTTP_REQUESTS_IN_FLIGHT.get();
+    let req = test::TestRequest::get().uri("/metrics-test-in-flight").to_request();
+    let during: i64 = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec())
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    assert_eq!(during, before + 1);
+    assert_eq!(HTTP_REQUESTS_IN_FLIGHT.get(), before);
+}
+
+async fn render_service() -> RenderService {
+    RenderService::new(offline_redis_pool().await, config(&[]))
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_render_records_duration_compile_time_and_errors() {
+    let service = render_service().await;
+    let variables = HashMap::from([("name".to_string(), json!("Ada"))]);
+
+    let renders = TEMPLATE_RENDER_DURATION.with_label_values(&["email_html"]).get_sample_count();
+    let compiles = TEMPLATE_COMPILE_DURATION.with_label_values(&["email_html"]).get_sample_count();
+    let shape_errors = TEMPLATE_RENDER_ERRORS.with_label_values(&["invalid_output"]).get();
+
+    let html = Template {
+        meta: Some(json!({ "sensitive": true })),
+        ..template("metrics-html", "email_html", "<p>Hi {{ name }} metrics-test-unique</p>")
+    };
+    service.render(&html, &variables).await.unwrap();
+    service.render(&html, &variables).await.unwrap();
+
+    let push = Template {
+        meta: Some(json!({ "sensitive": true })),
+        ..template("metrics-push", "push_json", r#"{"title": "{{ name }}"}"#)
+    };
+    assert!(service.render(&push, &variables).await.is_err());
+
+    assert_eq!(TEMPLATE_RENDER_DURATION.with_label_values(&["email_html"]).get_sample_count(), renders + 2);
+    assert_eq!(TEMPLATE_COMPILE_DURATION.with_label_values(&["email_html"]).get_sample_count(), compiles + 1);
+    assert_eq!(TEMPLATE_RENDER_ERRORS.with_label_values(&["invalid_output"]).get(), shape_errors + 1.0);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_db_queries_are_counted_on_completion_by_status() {
+    let ok = DB_QUERIES_TOTAL.with_label_values(&["metrics_test_query", "ok"]);
+    let error = DB_QUERIES_TOTAL.with_label_values(&["metrics_test_query", "error"]);
+
+    let query = async { Ok::<_, AppError>(1) }.db_query("metrics_test_query");
+    assert_eq!(ok.get(), 0.0);
+    assert_eq!(query.await.unwrap(), 1);
+    assert_eq!(ok.get(), 1.0);
+
+    let failed = async { Err::<i32, _>(AppError::TemplateNotFound) }
+        .db_query("metrics_test_query")
+        .await;
+    assert!(failed.is_err());
+    assert_eq!(error.get(), 1.0);
+    assert_eq!(ok.get(), 1.0);
+}
\ No newline at end of file
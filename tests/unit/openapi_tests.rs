@@ -0,0 +1,40 @@
+use templates_service::openapi::ApiDoc;
+use utoipa::OpenApi;
+
+#[test]
+fn test_documents_template_and_health_routes() {
+    let spec = ApiDoc::openapi();
+
+    for path in [
+        "/api/v1/templates/",
+        "/api/v1/templates/{template_code}",
+        "/api/v1/templates/{template_code}/render",
+        "/api/v1/templates/{template_code}/versions",
+        "/api/v1/templates/{template_code}/{version}",
+        "/health",
+        "/ready",
+    ] {
+        assert!(spec.paths.paths.contains_key(path), "{} is not documented", path);
+    }
+}
+
+#[test]
+fn test_declares_schemas_and_security_schemes() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let components = &spec["components"];
+
+    for schema in ["CreateTemplateRequest", "TemplateResponse", "RenderRequest", "ErrorResponse", "TemplateApiResponse"] {
+        assert!(components["schemas"][schema].is_object(), "{} schema missing", schema);
+    }
+    assert_eq!(components["securitySchemes"]["bearer_auth"]["bearerFormat"], "JWT");
+    assert_eq!(components["securitySchemes"]["api_key"]["name"], "X-API-Key");
+}
+
+
+#[test]
+fn test_lists_template_error_kinds() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let kinds = &spec["components"]["schemas"]["TemplateErrorKind"]["enum"];
+
+    assert_eq!(kinds, &serde_json::json!(["compile", "context", "render", "invalid_output"]));
+}
\ No newline at end of file
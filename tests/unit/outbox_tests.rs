@@ -0,0 +1,173 @@
+//! The `#[ignore]`d cases need a migrated Postgres at `DATABASE_URL` and,
+//! for successful dispatch, Redis at `REDIS_URL`: `cargo test -- --ignored`.
+
+use chrono::{DateTime, Utc};
+use serial_test::serial;
+use templates_service::cache::RedisPool;
+use templates_service::db::DbPool;
+use templates_service::models::{OutboxEvent, Template, WebhookEvent};
+use templates_service::services::outbox_dispatcher::{self, invalidation_targets, stream_fields};
+use templates_service::services::{OutboxDispatcher, TemplateService, WebhookService};
+use uuid::Uuid;
+
+use super::support::{config, db_pool, offline_redis_pool, redis_pool, template};
+
+fn sample_template() -> Template {
+    Template {
+        version: 3,
+        language: "de".to_string(),
+        ..template("welcome", "email_html", "<p>Hallo</p>")
+    }
+}
+
+#[test]
+fn test_created_event_invalidates_its_language() {
+    let event = WebhookEvent::template_created(&sample_template());
+
+    assert_eq!(
+        invalidation_targets(&event),
+        vec![("welcome".to_string(), 3, "de".to_string())]
+    );
+}
+
+#[test]
+fn test_deleted_event_invalidates_every_language() {
+    let languages = vec!["en".to_string(), "fr".to_string()];
+    let event = WebhookEvent::template_deleted("welcome", 3, &languages);
+
+    assert_eq!(
+        invalidation_targets(&event),
+        vec![
+            ("welcome".to_string(), 3, "en".to_string()),
+            ("welcome".to_string(), 3, "fr".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_or_malformed_events_invalidate_nothing() {
+    let unknown = WebhookEvent::new("template.renamed", serde_json::json!({"template_code": "a", "version": 1}));
+    assert!(invalidation_targets(&unknown).is_empty());
+
+    let malformed = WebhookEvent::new("template.created", serde_json::json!({"version": 1, "language": "en"}));
+    assert!(invalidation_targets(&malformed).is_empty());
+}
+
+#[test]
+fn test_stream_entry_carries_idempotency_key() {
+    let event = WebhookEvent::template_created(&sample_template());
+    let fields = stream_fields(&event);
+
+    assert_eq!(fields[0], ("event_id", event.id.to_string()));
+    assert_eq!(fields[1], ("type", "template.created".to_string()));
+    let data: serde_json::Value = serde_json::from_str(&fields[3].1).unwrap();
+    assert_eq!(data, event.data);
+}
+
+#[test]
+fn test_outbox_row_keeps_event_identity() {
+    let original = WebhookEvent::template_deleted("welcome", 3, &["en".to_string()]);
+    let row = OutboxEvent {
+        id: original.id,
+        event_type: original.event_type.clone(),
+        payload: original.data.clone(),
+        occurred_at: original.occurred_at,
+        attempts: 2,
+    };
+
+    assert_eq!(WebhookEvent::from(row), original);
+}
+
+fn dispatcher(pool: &DbPool, redis: &RedisPool) -> OutboxDispatcher {
+    let config = config(&[]);
+    OutboxDispatcher::new(
+        pool.clone(),
+        redis.clone(),
+        TemplateService::new(pool.clone(), redis.clone(), &config),
+        WebhookService::new(pool.clone(), &config),
+        &config,
+    )
+}
+
+async fn record_event(pool: &DbPool) -> WebhookEvent {
+    let code = format!("outbox-{}", Uuid::new_v4());
+    let event = WebhookEvent::template_created(&template(&code, "email_html", "<p>Hi</p>"));
+    let mut conn = pool.acquire().await.unwrap();
+    outbox_dispatcher::record(&mut conn, &event).await.unwrap();
+    event
+}
+
+struct OutboxState {
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    last_error: Option<String>,
+    dispatched_at: Option<DateTime<Utc>>,
+}
+
+async fn outbox_state(pool: &DbPool, event: &WebhookEvent) -> OutboxState {
+    let (attempts, next_attempt_at, last_error, dispatched_at) = sqlx::query_as(
+        "SELECT attempts, next_attempt_at, last_error, dispatched_at FROM outbox_events WHERE id = $1"
+    )
+    .bind(event.id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    OutboxState { attempts, next_attempt_at, last_error, dispatched_at }
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres and Redis"]
+async fn test_published_events_are_claimed_once_and_marked_dispatched() {
+    let pool = db_pool().await;
+    let dispatcher = dispatcher(&pool, &redis_pool().await);
+    let event = record_event(&pool).await;
+
+    assert!(dispatcher.dispatch_pending().await.unwrap() >= 1);
+    let state = outbox_state(&pool, &event).await;
+    assert_eq!(state.attempts, 1);
+    assert!(state.dispatched_at.is_some());
+    assert!(state.last_error.is_none());
+
+    dispatcher.dispatch_pending().await.unwrap();
+    assert_eq!(outbox_state(&pool, &event).await.attempts, 1);
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres"]
+async fn test_failed_cache_invalidation_schedules_a_retry() {
+    let pool = db_pool().await;
+    let dispatcher = dispatcher(&pool, &offline_redis_pool().await);
+    let event = record_event(&pool).await;
+
+    dispatcher.dispatch_pending().await.unwrap();
+    let state = outbox_state(&pool, &event).await;
+    assert_eq!(state.attempts, 1);
+    assert!(state.dispatched_at.is_none());
+    assert!(state.last_error.is_some());
+    assert!(state.next_attempt_at > Utc::now());
+
+    // Not due again until the backoff has passed.
+    dispatcher.dispatch_pending().await.unwrap();
+    assert_eq!(outbox_state(&pool, &event).await.attempts, 1);
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres and Redis"]
+async fn test_events_are_not_claimed_before_they_are_due() {
+    let pool = db_pool().await;
+    let dispatcher = dispatcher(&pool, &redis_pool().await);
+    let event = record_event(&pool).await;
+    sqlx::query("UPDATE outbox_events SET next_attempt_at = now() + interval '1 hour' WHERE id = $1")
+        .bind(event.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    dispatcher.dispatch_pending().await.unwrap();
+    let state = outbox_state(&pool, &event).await;
+    assert_eq!(state.attempts, 0);
+    assert!(state.dispatched_at.is_none());
+}
\ No newline at end of file
@@ -0,0 +1,155 @@
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, App, HttpMessage, HttpResponse};
+use serial_test::serial;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use templates_service::config::parse_limits;
+use templates_service::middleware::auth::{Principal, PrincipalKind};
+use templates_service::middleware::rate_limit::{
+    client_ip, client_key, sliding_window_estimate, AuthFailureLimit, RateLimitPolicy,
+};
+
+use super::support::redis_pool;
+
+#[test]
+fn test_sliding_window_weights_previous_window() {
+    assert_eq!(sliding_window_estimate(100, 10, 0, 60), 110);
+    assert_eq!(sliding_window_estimate(100, 10, 30, 60), 60);
+    assert_eq!(sliding_window_estimate(100, 10, 60, 60), 10);
+}
+
+#[test]
+fn test_limit_resolution_prefers_client_then_route() {
+    let policy = RateLimitPolicy {
+        window_secs: 60,
+        default_limit: 100,
+        route_limits: HashMap::from([("render_template".to_string(), 50)]),
+        client_limits: HashMap::from([("dispatcher".to_string(), 1000)]),
+        auth_failure_limit: 30,
+        trusted_proxies: vec![],
+    };
+
+    assert_eq!(policy.limit_for("render_template", "dispatcher"), 1000);
+    assert_eq!(policy.limit_for("render_template", "ip:10.0.0.1"), 50);
+    assert_eq!(policy.limit_for("get_template", "ip:10.0.0.1"), 100);
+}
+
+#[test]
+fn test_parse_limits() {
+    let limits = parse_limits("render_template=300, api_key:abc=5000").unwrap();
+    assert_eq!(limits.get("render_template"), Some(&300));
+    assert_eq!(limits.get("api_key:abc"), Some(&5000));
+    assert!(parse_limits("").unwrap().is_empty());
+    assert!(parse_limits("render_template").is_err());
+    assert!(parse_limits("render_template=lots").is_err());
+}
+
+
+fn policy(auth_failure_limit: u64, trusted_proxies: Vec<IpAddr>) -> Arc<RateLimitPolicy> {
+    Arc::new(RateLimitPolicy {
+        window_secs: 60,
+        default_limit: 100,
+        route_limits: HashMap::new(),
+        client_limits: HashMap::new(),
+        auth_failure_limit,
+        trusted_proxies,
+    })
+}
+
+#[test]
+fn test_client_key_ignores_unverified_api_key() {
+    let req = TestRequest::default()
+        .peer_addr("203.0.113.7:4000".parse().unwrap())
+        .insert_header(("X-API-Key", "made-up-key"))
+        .to_srv_request();
+
+    assert_eq!(client_key(&req, &[]), "ip:203.0.113.7");
+}
+
+#[test]
+fn test_client_key_prefers_verified_principal() {
+    let req = TestRequest::default()
+        .peer_addr("203.0.113.7:4000".parse().unwrap())
+        .to_srv_request();
+    req.extensions_mut().insert(Principal {
+        subject: "42".to_string(),
+        kind: PrincipalKind::ApiKey,
+        scopes: vec![],
+        tenant: None,
+    });
+
+    assert_eq!(client_key(&req, &[]), "api_key:42");
+}
+
+#[test]
+fn test_client_key_separates_principal_kinds() {
+    let key_for = |kind, subject: &str| {
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut().insert(Principal {
+            subject: subject.to_string(),
+            kind,
+            scopes: vec![],
+            tenant: None,
+        });
+        client_key(&req, &[])
+    };
+    let api_key = key_for(PrincipalKind::ApiKey, "42");
+
+    // A token issuer controls `sub`, so it must not be able to name an API
+    // key's bucket or override.
+    assert_eq!(key_for(PrincipalKind::Jwt, "42"), "jwt:42");
+    assert_ne!(key_for(PrincipalKind::Jwt, &api_key), api_key);
+    assert_eq!(key_for(PrincipalKind::Hmac, "secret_key"), "hmac:secret_key");
+}
+
+#[test]
+fn test_forwarded_for_only_trusted_from_configured_proxies() {
+    let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+    let request = |peer: &str| {
+        TestRequest::default()
+            .peer_addr(format!("{}:4000", peer).parse().unwrap())
+            .insert_header(("X-Forwarded-For", "198.51.100.1, 203.0.113.9, 10.0.0.2"))
+            .to_srv_request()
+    };
+
+    assert_eq!(client_ip(&request("203.0.113.7"), &[]), "203.0.113.7");
+    assert_eq!(client_ip(&request("203.0.113.7"), &[proxy]), "203.0.113.7");
+    // The left-most entry is whatever the client sent; the right-most
+    // untrusted hop is the address the proxy actually saw.
+    assert_eq!(client_ip(&request("10.0.0.2"), &[proxy]), "203.0.113.9");
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis"]
+async fn test_repeated_auth_rejections_are_throttled() {
+    let redis = redis_pool().await;
+    let peer = format!("192.0.2.{}:4000", chrono::Utc::now().timestamp_subsec_micros() % 250 + 1);
+    let app = test::init_service(
+        App::new()
+            .wrap(AuthFailureLimit::new(redis, policy(2, vec![])))
+            .route("/ok", web::get().to(|| async { HttpResponse::Ok().finish() }))
+            .route("/denied", web::get().to(|| async { HttpResponse::Unauthorized().finish() })),
+    )
+    .await;
+    let call = |path: &'static str| {
+        let peer = peer.clone();
+        let app = &app;
+        async move {
+            let req = TestRequest::get().uri(path).peer_addr(peer.parse().unwrap()).to_request();
+            match app.call(req).await {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            }
+        }
+    };
+
+    assert_eq!(call("/ok").await, StatusCode::OK);
+    assert_eq!(call("/denied").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(call("/denied").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(call("/denied").await, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(call("/ok").await, StatusCode::TOO_MANY_REQUESTS);
+}
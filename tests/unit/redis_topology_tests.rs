@@ -0,0 +1,123 @@
+//! Run against locally started Redis processes, e.g.
+//! `REDIS_MODE=cluster REDIS_CLUSTER_NODES=redis://127.0.0.1:7000 cargo test -- --ignored`
+//! or `REDIS_MODE=sentinel REDIS_SENTINEL_NODES=redis://127.0.0.1:26379 cargo test -- --ignored`.
+
+use redis::AsyncCommands;
+use serial_test::serial;
+use std::collections::HashSet;
+use templates_service::cache;
+
+use super::support::{config, redis_pool};
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis"]
+async fn test_rendered_entries_store_and_invalidate() {
+    let mut pool = redis_pool().await;
+    let key = cache::rendered_key("topology-test", 1, "en", "abc");
+
+    cache::store_rendered(&mut pool, "topology-test", 1, "en", &key, "payload", 60)
+        .await
+        .unwrap();
+    assert_eq!(
+        cache::rendered_members(&mut pool, "topology-test", 1, "en").await.unwrap(),
+        vec![key.clone()]
+    );
+
+    cache::invalidate_rendered(&mut pool, "topology-test", 1, "en").await.unwrap();
+    let cached: Option<String> = pool.get(&key).await.unwrap();
+    assert!(cached.is_none());
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis"]
+async fn test_invalidation_never_orphans_concurrent_stores() {
+    let pool = redis_pool().await;
+    let keys: Vec<String> = (0..200)
+        .map(|i| cache::rendered_key("topology-race", 1, "en", &i.to_string()))
+        .collect();
+
+    let mut tasks = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        let mut pool = pool.clone();
+        let key = key.clone();
+        tasks.push(tokio::spawn(async move {
+            cache::store_rendered(&mut pool, "topology-race", 1, "en", &key, "payload", 60)
+                .await
+                .unwrap();
+            if i % 10 == 0 {
+                cache::invalidate_rendered(&mut pool, "topology-race", 1, "en").await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // Every entry still cached must still be tracked, or the next
+    // invalidation would miss it.
+    let mut pool = pool.clone();
+    let tracked: HashSet<String> = cache::rendered_members(&mut pool, "topology-race", 1, "en")
+        .await
+        .unwrap()
+        .into_iter()
+        .collect();
+    for key in &keys {
+        if pool.exists::<_, bool>(key).await.unwrap() {
+            assert!(tracked.contains(key), "{} is cached but not tracked", key);
+        }
+    }
+
+    cache::invalidate_rendered(&mut pool, "topology-race", 1, "en").await.unwrap();
+    for key in &keys {
+        assert!(!pool.exists::<_, bool>(key).await.unwrap());
+    }
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis"]
+async fn test_delete_matching_reaches_every_node() {
+    let mut pool = redis_pool().await;
+    let keys: Vec<String> = (0..20)
+        .map(|i| cache::template_key(&format!("topology-flush-{}", i), None, "en"))
+        .collect();
+
+    for key in &keys {
+        let _: () = pool.set_ex(key, "x", 60).await.unwrap();
+    }
+
+    let mut deleted = cache::delete_matching(&mut pool, "template:{topology-flush-*").await.unwrap();
+    deleted.sort();
+    let mut expected = keys.clone();
+    expected.sort();
+    assert_eq!(deleted, expected);
+}
+
+#[actix_rt::test]
+async fn test_cluster_pubsub_fails_when_no_node_is_reachable() {
+    let config = config(&[
+        ("REDIS_MODE", "cluster"),
+        ("REDIS_CLUSTER_NODES", "redis://127.0.0.1:1,redis://127.0.0.1:2"),
+    ]);
+    let pool = cache::create_redis_pool(&config).await.unwrap();
+
+    assert!(pool.pubsub().await.is_err());
+}
+
+#[actix_rt::test]
+#[ignore = "requires a running Redis"]
+async fn test_cluster_pubsub_skips_unreachable_nodes() {
+    let reachable = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
+    let nodes = format!("redis://127.0.0.1:1,{}", reachable);
+    let config = config(&[("REDIS_MODE", "cluster"), ("REDIS_CLUSTER_NODES", &nodes)]);
+    let pool = cache::create_redis_pool(&config).await.unwrap();
+
+    // A reconnect starts after the node that succeeded and wraps around
+    // past the unreachable one.
+    for _ in 0..2 {
+        let mut pubsub = pool.pubsub().await.unwrap();
+        pubsub.subscribe(cache::INVALIDATION_CHANNEL).await.unwrap();
+    }
+}
\ No newline at end of file
@@ -1,4 +1,16 @@
-use templates_service::rendering::render_template;
+use actix_web::ResponseError;
+use templates_service::error::AppError;
+use templates_service::models::{TemplateErrorDetails, TemplateErrorKind};
+use templates_service::rendering::{compile, describe_tera_error};
+
+/// Renders `template` the way `RenderService` does: compiled by Tera and
+/// rendered against the JSON context.
+fn render_template(template: &str, context: &serde_json::Value) -> Result<String, Box<TemplateErrorDetails>> {
+    let tera = compile("test", template)?;
+    let context = tera::Context::from_value(context.clone()).unwrap();
+    tera.render("test", &context)
+        .map_err(|e| Box::new(describe_tera_error(TemplateErrorKind::Render, &e, "test", template)))
+}
 
 #[test]
 fn test_render_simple_template() {
@@ -18,7 +30,9 @@ fn test_render_with_missing_variable() {
     let context = serde_json::json!({});
     
     let result = render_template(template, &context);
-    assert!(result.is_ok());
+    let details = result.unwrap_err();
+    assert_eq!(details.kind, TemplateErrorKind::Render);
+    assert!(details.message.contains("`name` not found"));
 }
 
 #[test]
@@ -54,4 +68,54 @@ fn test_render_no_variables() {
     let result = render_template(template, &context);
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Static content without variables");
+}
+
+#[test]
+fn test_render_filters_and_tags() {
+    let template = "{% for item in items %}{{ item | upper }}{% if not loop.last %}, {% endif %}{% endfor %}";
+    let context = serde_json::json!({ "items": ["a", "b"] });
+
+    let result = render_template(template, &context);
+    assert_eq!(result.unwrap(), "A, B");
+}
+
+#[test]
+fn test_compile_reports_syntax_error_location() {
+    let content = "<p>Hi</p>\n{% for %}\n";
+    let details = compile("html_0123abcd", content).err().unwrap();
+
+    assert_eq!(details.kind, TemplateErrorKind::Compile);
+    assert_eq!(details.line, Some(2));
+    assert_eq!(details.column, Some(8));
+    assert_eq!(details.snippet.as_deref(), Some("{% for %}"));
+    assert_eq!(details.message, "expected an identifier (must start with a-z)");
+    assert_eq!(details.causes, vec!["Failed to parse", "expected an identifier (must start with a-z)"]);
+}
+
+#[test]
+fn test_render_error_details_hide_internal_name() {
+    let tera = compile("html_0123abcd", "Hello {{ name }}").unwrap();
+    let err = tera.render("html_0123abcd", &tera::Context::new()).unwrap_err();
+    let details = describe_tera_error(TemplateErrorKind::Render, &err, "html_0123abcd", "Hello {{ name }}");
+
+    assert_eq!(details.kind, TemplateErrorKind::Render);
+    assert_eq!(details.line, None);
+    assert_eq!(details.message, "Variable `name` not found in context while rendering");
+    assert!(details.causes.iter().all(|c| !c.contains("html_0123abcd")));
+}
+
+#[actix_rt::test]
+async fn test_syntax_error_envelope() {
+    let details = compile("welcome", "Hello {{ name").err().unwrap();
+    let response = AppError::TemplateSyntaxError(details).error_response();
+    assert_eq!(response.status(), 400);
+
+    let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"], "template_syntax_error");
+    assert_eq!(body["details"]["kind"], "compile");
+    assert_eq!(body["details"]["line"], 1);
+    assert_eq!(body["details"]["column"], 14);
+    assert_eq!(body["details"]["snippet"], "Hello {{ name");
+    assert!(body["message"].as_str().unwrap().starts_with("Template syntax error at line 1, column 14"));
 }
\ No newline at end of file
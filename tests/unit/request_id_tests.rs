@@ -0,0 +1,57 @@
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::Value;
+use templates_service::error::AppError;
+use templates_service::middleware::request_id::resolve_request_id;
+use templates_service::middleware::RequestId;
+use templates_service::models::ApiResponse;
+use uuid::Uuid;
+
+#[actix_rt::test]
+async fn test_resolve_request_id() {
+    assert_eq!(resolve_request_id(Some(" abc-123 ")), "abc-123");
+    for rejected in [None, Some(""), Some("has space"), Some("bad\u{7f}")] {
+        assert!(Uuid::parse_str(&resolve_request_id(rejected)).is_ok());
+    }
+    assert!(Uuid::parse_str(&resolve_request_id(Some(&"a".repeat(129)))).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_request_id_in_headers_and_envelopes() {
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestId)
+            .route(
+                "/ok",
+                web::get().to(|| async { HttpResponse::Ok().json(ApiResponse::success(1, "ok")) }),
+            )
+            .route(
+                "/missing",
+                web::get().to(|| async { Err::<HttpResponse, _>(AppError::TemplateNotFound) }),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/ok")
+        .insert_header(("X-Request-Id", "client-supplied-1"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "client-supplied-1");
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["request_id"], "client-supplied-1");
+
+    let res = test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+    assert_eq!(res.status(), 404);
+    let header = res.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    assert!(Uuid::parse_str(&header).is_ok());
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"], "template_not_found");
+    assert_eq!(body["request_id"], header.as_str());
+}
+
+#[actix_rt::test]
+async fn test_no_request_id_outside_a_request() {
+    let response = ApiResponse::success(1, "ok");
+    assert!(response.request_id.is_none());
+    assert!(serde_json::to_value(&response).unwrap().get("request_id").is_none());
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+//! Read and ops routes are wired as in `main`: `Auth` behind a `Condition`
+//! on `AUTH_REQUIRE_READ` / `AUTH_PROTECT_OPS`; writes are always protected.
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Condition;
+use actix_web::{test, web, App, Error, HttpResponse};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use std::sync::Arc;
+use templates_service::middleware::auth::scopes;
+use templates_service::middleware::{Auth, JwtVerifier};
+use templates_service::services::ApiKeyService;
+
+use super::support::offline_db_pool;
+
+const SECRET: &str = "route-auth-secret";
+
+fn routes(cfg: &mut web::ServiceConfig, require_read: bool, protect_ops: bool) {
+    let verifier = Arc::new(JwtVerifier::new(Some(SECRET), None, None, vec![]));
+    let api_keys = Arc::new(ApiKeyService::new(offline_db_pool()));
+    let auth = |scope| Auth::new(verifier.clone(), api_keys.clone()).require_scope(scope);
+    let read_auth = || Condition::new(require_read, auth(scopes::READ));
+    let ops_auth = || Condition::new(protect_ops, auth(scopes::OPS));
+
+    cfg.route("/health", web::get().to(HttpResponse::Ok).wrap(ops_auth()))
+        .route("/metrics", web::get().to(HttpResponse::Ok).wrap(ops_auth()))
+        .service(
+            web::scope("/api/v1/templates")
+                .route("", web::post().to(HttpResponse::Created).wrap(auth(scopes::WRITE)))
+                .route("/{code}", web::get().to(HttpResponse::Ok).wrap(read_auth())),
+        );
+}
+
+fn token(scope: &str) -> String {
+    let claims = serde_json::json!({
+        "sub": "user-1",
+        "exp": chrono::Utc::now().timestamp() + 600,
+        "type": "access",
+        "scope": scope,
+    });
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+}
+
+async fn status<S, R>(app: &S, req: R) -> StatusCode
+where
+    S: Service<R, Response = ServiceResponse, Error = Error>,
+{
+    match test::try_call_service(app, req).await {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    }
+}
+
+fn request(method: &Method, uri: &str, scope: Option<&str>) -> test::TestRequest {
+    let req = test::TestRequest::default().method(method.clone()).uri(uri);
+    match scope {
+        Some(scope) => req.insert_header(("Authorization", format!("Bearer {}", token(scope)))),
+        None => req,
+    }
+}
+
+async fn assert_statuses(
+    require_read: bool,
+    protect_ops: bool,
+    cases: &[(Method, &str, Option<&str>, StatusCode)],
+) {
+    let app = test::init_service(App::new().configure(|cfg| routes(cfg, require_read, protect_ops))).await;
+
+    for (method, uri, scope, expected) in cases {
+        let actual = status(&app, request(method, uri, *scope).to_request()).await;
+        assert_eq!(actual, *expected, "{} {} with scope {:?}", method, uri, scope);
+    }
+}
+
+#[actix_rt::test]
+async fn test_read_and_ops_routes_open_by_default() {
+    assert_statuses(false, false, &[
+        (Method::GET, "/api/v1/templates/welcome", None, StatusCode::OK),
+        (Method::GET, "/health", None, StatusCode::OK),
+        (Method::GET, "/metrics", None, StatusCode::OK),
+        (Method::POST, "/api/v1/templates", None, StatusCode::UNAUTHORIZED),
+        (Method::POST, "/api/v1/templates", Some(scopes::WRITE), StatusCode::CREATED),
+    ])
+    .await;
+}
+
+#[actix_rt::test]
+async fn test_require_read_protects_read_routes_only() {
+    assert_statuses(true, false, &[
+        (Method::GET, "/api/v1/templates/welcome", None, StatusCode::UNAUTHORIZED),
+        (Method::GET, "/api/v1/templates/welcome", Some(scopes::OPS), StatusCode::FORBIDDEN),
+        (Method::GET, "/api/v1/templates/welcome", Some(scopes::READ), StatusCode::OK),
+        (Method::GET, "/health", None, StatusCode::OK),
+    ])
+    .await;
+}
+
+#[actix_rt::test]
+async fn test_protect_ops_requires_ops_scope() {
+    assert_statuses(false, true, &[
+        (Method::GET, "/health", None, StatusCode::UNAUTHORIZED),
+        (Method::GET, "/metrics", Some(scopes::READ), StatusCode::FORBIDDEN),
+        (Method::GET, "/metrics", Some(scopes::OPS), StatusCode::OK),
+        (Method::GET, "/health", Some(scopes::ADMIN), StatusCode::OK),
+        (Method::GET, "/api/v1/templates/welcome", None, StatusCode::OK),
+    ])
+    .await;
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+//! The `#[ignore]`d cases need Redis at `REDIS_URL` for the nonce check:
+//! `cargo test -- --ignored`.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::test::{init_service, read_body, try_call_service, TestRequest};
+use actix_web::{web, App, Error, HttpResponse};
+use std::sync::Arc;
+use templates_service::cache::RedisPool;
+use templates_service::middleware::auth::scopes;
+use templates_service::middleware::signature::{
+    sign, string_to_sign, verify, NONCE_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use templates_service::middleware::{Auth, JwtVerifier, Signature};
+use templates_service::services::ApiKeyService;
+use uuid::Uuid;
+
+use super::support::{offline_db_pool, offline_redis_pool, redis_pool};
+
+const SECRET: &str = "signature-secret";
+const TOLERANCE_SECS: u64 = 300;
+
+/// Signed callers are granted only `templates:read`.
+fn signed_app(
+    redis: RedisPool,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let verifier = Arc::new(JwtVerifier::new(Some(SECRET), None, None, vec![]));
+    let api_keys = Arc::new(ApiKeyService::new(offline_db_pool()));
+    let auth = |scope| Auth::new(verifier.clone(), api_keys.clone()).require_scope(scope);
+
+    App::new()
+        .wrap(Signature::new(
+            SECRET.to_string(),
+            redis,
+            TOLERANCE_SECS,
+            vec![scopes::READ.to_string()],
+        ))
+        .route("/echo", web::post().to(|body: String| async move { body }))
+        .route("/read", web::post().to(HttpResponse::Ok).wrap(auth(scopes::READ)))
+        .route("/write", web::post().to(HttpResponse::Created).wrap(auth(scopes::WRITE)))
+}
+
+/// A request to `uri` signed over `signed_body` but carrying `body`.
+fn signed_request(uri: &str, signed_body: &str, body: &str, timestamp: i64, nonce: &str) -> TestRequest {
+    let timestamp = timestamp.to_string();
+    let canonical = string_to_sign("POST", uri, &timestamp, nonce, signed_body.as_bytes());
+
+    TestRequest::post()
+        .uri(uri)
+        .insert_header((SIGNATURE_HEADER, sign(SECRET, &canonical)))
+        .insert_header((TIMESTAMP_HEADER, timestamp))
+        .insert_header((NONCE_HEADER, nonce))
+        .set_payload(body.to_string())
+}
+
+/// A correctly signed request with a fresh nonce.
+fn fresh_request(uri: &str, body: &str) -> TestRequest {
+    signed_request(uri, body, body, now(), &Uuid::new_v4().to_string())
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Status and body, whether the middleware rejected the request or not.
+async fn outcome<S, R, B>(app: &S, req: R) -> (StatusCode, String)
+where
+    S: Service<R, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let (status, body) = match try_call_service(app, req).await {
+        Ok(res) => (res.status(), read_body(res).await),
+        Err(e) => {
+            let res = e.error_response();
+            (res.status(), actix_web::body::to_bytes(res.into_body()).await.unwrap())
+        }
+    };
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[test]
+fn test_signature_round_trip() {
+    let canonical = string_to_sign("post", "/api/v1/templates/welcome/render?language=en", "1700000000", "n-1", br#"{"variables":{}}"#);
+    let signature = sign("secret", &canonical);
+
+    assert!(canonical.starts_with("POST\n/api/v1/templates/welcome/render?language=en\n1700000000\nn-1\n"));
+    assert!(verify("secret", &canonical, &signature));
+}
+
+#[test]
+fn test_signature_rejects_tampering() {
+    let canonical = string_to_sign("POST", "/api/v1/templates/", "1700000000", "n-1", b"original");
+    let signature = sign("secret", &canonical);
+
+    let tampered_body = string_to_sign("POST", "/api/v1/templates/", "1700000000", "n-1", b"modified");
+    let tampered_nonce = string_to_sign("POST", "/api/v1/templates/", "1700000000", "n-2", b"original");
+
+    assert!(!verify("secret", &tampered_body, &signature));
+    assert!(!verify("secret", &tampered_nonce, &signature));
+    assert!(!verify("other-secret", &canonical, &signature));
+    assert!(!verify("secret", &canonical, "not-hex"));
+}
+
+
+#[actix_rt::test]
+async fn test_unsigned_requests_pass_through() {
+    let app = init_service(signed_app(offline_redis_pool().await)).await;
+    let req = TestRequest::post().uri("/echo").set_payload("hello");
+
+    let (status, body) = outcome(&app, req.to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "hello");
+}
+
+#[actix_rt::test]
+async fn test_rejects_missing_timestamp_or_nonce() {
+    let app = init_service(signed_app(offline_redis_pool().await)).await;
+
+    for missing in [TIMESTAMP_HEADER, NONCE_HEADER] {
+        let mut req = fresh_request("/echo", "hello").to_request();
+        req.headers_mut().remove(missing);
+
+        let (status, body) = outcome(&app, req).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "without {}", missing);
+        assert!(body.contains("Missing signature timestamp or nonce"), "{}", body);
+    }
+}
+
+#[actix_rt::test]
+async fn test_rejects_timestamps_outside_the_window() {
+    let app = init_service(signed_app(offline_redis_pool().await)).await;
+    let tolerance = TOLERANCE_SECS as i64;
+
+    for timestamp in [now() - tolerance - 5, now() + tolerance + 5] {
+        let req = signed_request("/echo", "hello", "hello", timestamp, &Uuid::new_v4().to_string());
+        let (status, body) = outcome(&app, req.to_request()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(body.contains("Signature timestamp outside allowed window"), "{}", body);
+    }
+}
+
+#[actix_rt::test]
+async fn test_rejects_tampered_body() {
+    let app = init_service(signed_app(offline_redis_pool().await)).await;
+    let req = signed_request("/echo", "hello", "goodbye", now(), &Uuid::new_v4().to_string());
+
+    let (status, body) = outcome(&app, req.to_request()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("Invalid request signature"), "{}", body);
+}
+
+#[actix_rt::test]
+async fn test_fails_closed_when_nonces_cannot_be_checked() {
+    let app = init_service(signed_app(offline_redis_pool().await)).await;
+
+    let (status, body) = outcome(&app, fresh_request("/echo", "hello").to_request()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("Unable to verify request signature"), "{}", body);
+}
+
+#[actix_rt::test]
+#[ignore = "requires a running Redis"]
+async fn test_rejects_a_replayed_nonce() {
+    let app = init_service(signed_app(redis_pool().await)).await;
+    let nonce = Uuid::new_v4().to_string();
+    let timestamp = now();
+    let req = || signed_request("/echo", "hello", "hello", timestamp, &nonce).to_request();
+
+    let (status, body) = outcome(&app, req()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "hello");
+
+    let (status, body) = outcome(&app, req()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("Request nonce already used"), "{}", body);
+}
+
+#[actix_rt::test]
+#[ignore = "requires a running Redis"]
+async fn test_signed_callers_only_get_configured_scopes() {
+    let app = init_service(signed_app(redis_pool().await)).await;
+
+    let (status, _) = outcome(&app, fresh_request("/read", "").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = outcome(&app, fresh_request("/write", "").to_request()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(body.contains("Missing required scope: templates:write"), "{}", body);
+}
\ No newline at end of file
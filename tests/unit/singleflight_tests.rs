@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use templates_service::error::AppError;
+use templates_service::singleflight::SingleFlight;
+
+/// A finished load must not linger: the next call on `key` loads afresh.
+async fn assert_released(flight: &SingleFlight<String>, key: &str) {
+    let fresh = flight.run(key, || async { Ok("fresh".to_string()) }).await;
+    assert_eq!(fresh.unwrap(), "fresh");
+}
+
+#[actix_rt::test]
+async fn test_concurrent_loads_are_coalesced() {
+    let flight = Arc::new(SingleFlight::<String>::new());
+    let loads = Arc::new(AtomicUsize::new(0));
+
+    let calls = (0..10).map(|_| {
+        let flight = flight.clone();
+        let loads = loads.clone();
+        async move {
+            flight
+                .run("template:welcome:latest:en", || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok("loaded".to_string())
+                })
+                .await
+        }
+    });
+
+    let results = futures::future::join_all(calls).await;
+
+    assert!(results.iter().all(|r| r.as_deref().ok() == Some("loaded")));
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+    assert_released(&flight, "template:welcome:latest:en").await;
+}
+
+#[actix_rt::test]
+async fn test_not_found_is_shared_with_followers() {
+    let flight = Arc::new(SingleFlight::<String>::new());
+    let loads = Arc::new(AtomicUsize::new(0));
+
+    let calls = (0..10).map(|_| {
+        let flight = flight.clone();
+        let loads = loads.clone();
+        async move {
+            flight
+                .run("template:missing:latest:en", || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err(AppError::TemplateNotFound)
+                })
+                .await
+        }
+    });
+
+    let results = futures::future::join_all(calls).await;
+
+    assert!(results.iter().all(|r| matches!(r, Err(AppError::TemplateNotFound))));
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+    assert_released(&flight, "template:missing:latest:en").await;
+}
+
+#[actix_rt::test]
+async fn test_followers_retry_when_leader_error_cannot_be_shared() {
+    let flight = Arc::new(SingleFlight::<String>::new());
+    let loads = Arc::new(AtomicUsize::new(0));
+
+    let leader = {
+        let flight = flight.clone();
+        let loads = loads.clone();
+        async move {
+            flight
+                .run("k", || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err(AppError::DatabaseError(sqlx::Error::PoolTimedOut))
+                })
+                .await
+        }
+    };
+    let follower = {
+        let flight = flight.clone();
+        let loads = loads.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            flight
+                .run("k", || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    Ok("recovered".to_string())
+                })
+                .await
+        }
+    };
+
+    let (leader, follower) = futures::join!(leader, follower);
+
+    assert!(leader.is_err());
+    assert_eq!(follower.unwrap(), "recovered");
+    assert_eq!(loads.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,165 @@
+//! Fixtures shared by the unit tests.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::mpsc;
+use std::time::Duration;
+use templates_service::cache::{self, RedisPool};
+use templates_service::config::Config;
+use templates_service::db::{self, DbPool};
+use templates_service::models::Template;
+use uuid::Uuid;
+
+/// A request received by `spawn_http_stub`.
+pub struct CapturedRequest {
+    pub request_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl CapturedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Serves every request on a local port with `status` (e.g. `"200 OK"`) and
+/// a JSON `body`, reporting each request received. Returns the base URL.
+pub fn spawn_http_stub(status: &'static str, body: &'static str) -> (String, mpsc::Receiver<CapturedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut headers = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            let _ = reader.read_exact(&mut request_body);
+
+            let mut stream = reader.into_inner();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+            let _ = tx.send(CapturedRequest {
+                request_line: request_line.trim().to_string(),
+                headers,
+                body: request_body,
+            });
+        }
+    });
+
+    (base_url, rx)
+}
+
+/// An active version-1 `en` template.
+pub fn template(code: &str, template_type: &str, content: &str) -> Template {
+    Template {
+        id: Uuid::new_v4(),
+        template_code: code.to_string(),
+        version: 1,
+        template_type: template_type.to_string(),
+        language: "en".to_string(),
+        content: content.to_string(),
+        created_by: None,
+        created_at: chrono::Utc::now(),
+        updated_at: None,
+        is_active: true,
+        meta: None,
+    }
+}
+
+/// A config read from `overrides`, then defaults that satisfy the required
+/// settings. The process environment is neither read nor written, so tests
+/// behave the same wherever they run and cannot race on it in parallel.
+pub fn config(overrides: &[(&str, &str)]) -> Config {
+    let defaults = [
+        ("DATABASE_URL", "postgres://localhost/unused"),
+        ("REDIS_URL", "redis://127.0.0.1:6379"),
+        ("SECRET_KEY", "test-secret"),
+        ("JWT_SECRET", "test-secret"),
+    ];
+
+    Config::from_lookup(|key| {
+        overrides
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.to_string())
+            .or_else(|| defaults.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string()))
+    })
+}
+
+/// A pool for the Redis at `REDIS_URL` (default `redis://127.0.0.1:6379`),
+/// for `#[ignore]`d tests run with `cargo test -- --ignored`. The topology
+/// settings are taken from the environment too, so the same tests can run
+/// against Cluster or Sentinel.
+pub async fn redis_pool() -> RedisPool {
+    let settings: Vec<(&str, String)> = [
+        "REDIS_URL",
+        "REDIS_MODE",
+        "REDIS_CLUSTER_NODES",
+        "REDIS_SENTINEL_NODES",
+        "REDIS_SENTINEL_MASTER",
+    ]
+    .into_iter()
+    .filter_map(|key| std::env::var(key).ok().map(|value| (key, value)))
+    .collect();
+    let overrides: Vec<(&str, &str)> = settings.iter().map(|(key, value)| (*key, value.as_str())).collect();
+
+    let pool = cache::create_redis_pool(&config(&overrides)).await.unwrap();
+    assert!(pool.is_available(), "Redis is not reachable with the configured topology");
+    pool
+}
+
+/// A pool whose Redis is unreachable, for code paths that must degrade
+/// without it.
+pub async fn offline_redis_pool() -> RedisPool {
+    let config = config(&[("REDIS_URL", "redis://127.0.0.1:1"), ("REDIS_MODE", "standalone")]);
+    cache::create_redis_pool(&config).await.unwrap()
+}
+
+/// A pool for the migrated Postgres at `DATABASE_URL`, for `#[ignore]`d
+/// tests run with `cargo test -- --ignored`.
+pub async fn db_pool() -> DbPool {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    db::create_pool(&url).await.unwrap()
+}
+
+/// A pool that never connects, so any query through it fails fast.
+pub fn offline_db_pool() -> DbPool {
+    PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(200))
+        .connect_lazy("postgres://127.0.0.1:1/unused")
+        .unwrap()
+}
\ No newline at end of file
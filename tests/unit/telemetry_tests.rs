@@ -0,0 +1,68 @@
+use actix_web::{test as actix_test, web, App, HttpResponse};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::time::Duration;
+use templates_service::middleware::TraceContext;
+use templates_service::telemetry;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+use super::support::spawn_http_stub;
+
+#[test]
+fn test_traces_endpoint() {
+    assert_eq!(telemetry::traces_endpoint("http://collector:4318"), "http://collector:4318/v1/traces");
+    assert_eq!(telemetry::traces_endpoint("http://collector:4318/"), "http://collector:4318/v1/traces");
+}
+
+#[test]
+fn test_spans_are_exported_to_collector() {
+    let (endpoint, requests) = spawn_http_stub("200 OK", "");
+    let provider = telemetry::tracer_provider(&endpoint, "templates-service-test", 1.0).unwrap();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+    tracing::subscriber::with_default(subscriber, || {
+        let _span = telemetry::db_span("select_template").entered();
+    });
+    provider.force_flush().unwrap();
+
+    let request = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(request.request_line, "POST /v1/traces HTTP/1.1");
+    let body = request.body_text();
+    assert!(body.contains("templates-service-test"));
+    assert!(body.contains("postgres select_template"));
+
+    provider.shutdown().unwrap();
+}
+
+#[actix_rt::test]
+async fn test_traceparent_continues_caller_trace() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = actix_test::init_service(App::new().wrap(TraceContext).route(
+        "/trace-test",
+        web::get().to(|| async {
+            let context = tracing::Span::current().context();
+            HttpResponse::Ok().body(context.span().span_context().trace_id().to_string())
+        }),
+    ))
+    .await;
+
+    let req = actix_test::TestRequest::get()
+        .uri("/trace-test")
+        .insert_header(("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"))
+        .to_request();
+    let body = actix_test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+    let req = actix_test::TestRequest::get().uri("/trace-test").to_request();
+    let body = actix_test::call_and_read_body(&app, req).await;
+    assert_ne!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_ne!(body, "00000000000000000000000000000000");
+}
\ No newline at end of file
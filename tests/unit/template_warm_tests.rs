@@ -0,0 +1,68 @@
+//! Cache warm-up must not publish a snapshot as the latest version. The
+//! `#[ignore]`d cases need a migrated Postgres at `DATABASE_URL`:
+//! `DATABASE_URL=postgres://localhost/templates_db cargo test -- --ignored`.
+
+use templates_service::error::AppError;
+use templates_service::models::CreateTemplateRequest;
+use templates_service::services::TemplateService;
+use uuid::Uuid;
+
+use super::support::{config, db_pool, offline_db_pool, offline_redis_pool, template};
+
+fn create_request(code: &str, content: &str) -> CreateTemplateRequest {
+    CreateTemplateRequest {
+        template_code: code.to_string(),
+        template_type: "email_html".to_string(),
+        language: "en".to_string(),
+        content: content.to_string(),
+        meta: None,
+    }
+}
+
+#[actix_rt::test]
+async fn test_warm_caches_the_version_but_not_latest_from_the_snapshot() {
+    let service = TemplateService::new(offline_db_pool(), offline_redis_pool().await, &config(&[]));
+    let snapshot = template("warm-snapshot", "email_html", "<p>v1</p>");
+
+    assert!(matches!(service.warm(&snapshot).await, Err(AppError::DatabaseError(_))));
+
+    let versioned = service.get_template("warm-snapshot", Some("en"), Some(1)).await.unwrap();
+    assert_eq!(versioned.content, "<p>v1</p>");
+    assert!(matches!(
+        service.get_template("warm-snapshot", Some("en"), None).await,
+        Err(AppError::DatabaseError(_))
+    ));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_warming_a_superseded_version_keeps_latest_current() {
+    let service = TemplateService::new(db_pool().await, offline_redis_pool().await, &config(&[]));
+    let code = format!("warm-{}", Uuid::new_v4());
+
+    let v1 = service.create_template(create_request(&code, "<p>v1</p>")).await.unwrap();
+    service.create_template(create_request(&code, "<p>v2</p>")).await.unwrap();
+
+    service.warm(&v1).await.unwrap();
+
+    let latest = service.get_template(&code, Some("en"), None).await.unwrap();
+    assert_eq!(latest.version, 2);
+    assert_eq!(service.get_template(&code, Some("en"), Some(1)).await.unwrap().content, "<p>v1</p>");
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_warming_a_deleted_template_does_not_cache_latest() {
+    let service = TemplateService::new(db_pool().await, offline_redis_pool().await, &config(&[]));
+    let code = format!("warm-{}", Uuid::new_v4());
+
+    let v1 = service.create_template(create_request(&code, "<p>v1</p>")).await.unwrap();
+    service.soft_delete(&code, 1).await.unwrap();
+
+    service.warm(&v1).await.unwrap();
+
+    assert!(matches!(
+        service.get_template(&code, Some("en"), None).await,
+        Err(AppError::TemplateNotFound)
+    ));
+}
\ No newline at end of file
@@ -0,0 +1,186 @@
+//! The `#[ignore]`d cases need Redis at `REDIS_URL` and a migrated Postgres
+//! at `DATABASE_URL`: `cargo test -- --ignored`.
+
+use actix_web::http::StatusCode;
+use actix_web::test::{call_and_read_body_json, call_service, init_service, TestRequest};
+use actix_web::{web, App};
+use chrono::{TimeZone, Utc};
+use redis::AsyncCommands;
+use serial_test::serial;
+use std::collections::HashMap;
+use std::time::Duration;
+use templates_service::error::AppError;
+use templates_service::handlers::{get_template_usage, list_unused_templates};
+use templates_service::models::{CreateTemplateRequest, TemplateUsage};
+use templates_service::services::usage_service::{
+    parse_usage_batch, usage_field, UsageDelta, FLUSHING_USAGE_PREFIX, PENDING_USAGE_KEY,
+};
+use templates_service::services::{TemplateService, UsageService};
+use uuid::Uuid;
+
+use super::support::{config, db_pool, offline_db_pool, offline_redis_pool, redis_pool};
+
+#[test]
+fn test_usage_field_is_unambiguous() {
+    assert_ne!(usage_field("a:1", 2, "en"), usage_field("a", 1, "2:en"));
+    assert_eq!(usage_field("welcome", 3, "en"), r#"["welcome",3,"en"]"#);
+}
+
+#[test]
+fn test_parse_usage_batch() {
+    let flushed_at = Utc.timestamp_millis_opt(2_000_000).unwrap();
+    let welcome = usage_field("welcome", 2, "en");
+    let reset = usage_field("reset|password", 1, "fr");
+
+    let mut fields = HashMap::new();
+    fields.insert(format!("c:{}", welcome), "5".to_string());
+    fields.insert(format!("l:{}", welcome), "1000000".to_string());
+    fields.insert(format!("c:{}", reset), "1".to_string());
+    fields.insert("c:not-json".to_string(), "3".to_string());
+    fields.insert(format!("c:{}", usage_field("zero", 1, "en")), "0".to_string());
+
+    let deltas = parse_usage_batch(&fields, flushed_at);
+
+    assert_eq!(
+        deltas,
+        vec![
+            UsageDelta {
+                template_code: "reset|password".to_string(),
+                version: 1,
+                language: "fr".to_string(),
+                renders: 1,
+                last_rendered_at: flushed_at,
+            },
+            UsageDelta {
+                template_code: "welcome".to_string(),
+                version: 2,
+                language: "en".to_string(),
+                renders: 5,
+                last_rendered_at: Utc.timestamp_millis_opt(1_000_000).unwrap(),
+            },
+        ]
+    );
+}
+
+async fn create_template(code: &str) {
+    let templates = TemplateService::new(db_pool().await, offline_redis_pool().await, &config(&[]));
+    templates
+        .create_template(CreateTemplateRequest {
+            template_code: code.to_string(),
+            template_type: "email_html".to_string(),
+            language: "en".to_string(),
+            content: "<p>Hi</p>".to_string(),
+            meta: None,
+        })
+        .await
+        .unwrap();
+}
+
+fn count_field(code: &str) -> String {
+    format!("c:{}", usage_field(code, 1, "en"))
+}
+
+fn render_count(usage: &[TemplateUsage]) -> i64 {
+    usage.iter().map(|u| u.render_count).sum()
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis and a migrated Postgres"]
+async fn test_flush_moves_pending_counts_to_postgres() {
+    let mut redis = redis_pool().await;
+    let service = UsageService::new(db_pool().await, redis.clone(), true);
+    let code = format!("usage-{}", Uuid::new_v4());
+    create_template(&code).await;
+    let _: () = redis.del(PENDING_USAGE_KEY).await.unwrap();
+
+    let _: () = redis.hincr(PENDING_USAGE_KEY, count_field(&code), 3).await.unwrap();
+    assert_eq!(service.flush().await.unwrap(), 1);
+    let _: () = redis.hincr(PENDING_USAGE_KEY, count_field(&code), 2).await.unwrap();
+    assert_eq!(service.flush().await.unwrap(), 1);
+
+    assert_eq!(render_count(&service.usage_for(&code).await.unwrap()), 5);
+    assert!(!redis.exists::<_, bool>(PENDING_USAGE_KEY).await.unwrap());
+    assert_eq!(service.flush().await.unwrap(), 0);
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis"]
+async fn test_failed_flush_restores_counts() {
+    let mut redis = redis_pool().await;
+    let service = UsageService::new(offline_db_pool(), redis.clone(), true);
+    let _: () = redis.del(PENDING_USAGE_KEY).await.unwrap();
+
+    let _: () = redis.hincr(PENDING_USAGE_KEY, count_field("restored"), 4).await.unwrap();
+    assert!(matches!(service.flush().await, Err(AppError::DatabaseError(_))));
+
+    let restored: HashMap<String, i64> = redis.hgetall(PENDING_USAGE_KEY).await.unwrap();
+    assert_eq!(restored.get(&count_field("restored")), Some(&4));
+    let _: () = redis.del(PENDING_USAGE_KEY).await.unwrap();
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a running Redis and a migrated Postgres"]
+async fn test_recovers_batches_left_by_an_interrupted_flush() {
+    let mut redis = redis_pool().await;
+    let service = UsageService::new(db_pool().await, redis.clone(), true);
+    let code = format!("usage-{}", Uuid::new_v4());
+    create_template(&code).await;
+
+    let stale = Utc::now().timestamp_millis() - 600_000;
+    let stale_key = format!("{}{}:{}", FLUSHING_USAGE_PREFIX, stale, Uuid::new_v4());
+    let fresh_key = format!("{}{}:{}", FLUSHING_USAGE_PREFIX, Utc::now().timestamp_millis(), Uuid::new_v4());
+    let _: () = redis.hincr(&stale_key, count_field(&code), 7).await.unwrap();
+    let _: () = redis.hincr(&fresh_key, count_field(&code), 1).await.unwrap();
+
+    assert_eq!(service.recover_batches(Duration::from_secs(300)).await.unwrap(), 1);
+
+    assert_eq!(render_count(&service.usage_for(&code).await.unwrap()), 7);
+    assert!(!redis.exists::<_, bool>(&stale_key).await.unwrap());
+    assert!(redis.exists::<_, bool>(&fresh_key).await.unwrap());
+    let _: () = redis.del(&fresh_key).await.unwrap();
+}
+
+#[actix_rt::test]
+async fn test_unused_endpoint_rejects_negative_days() {
+    let service = UsageService::new(offline_db_pool(), offline_redis_pool().await, false);
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/unused", web::get().to(list_unused_templates)),
+    )
+    .await;
+
+    let res = call_service(&app, TestRequest::get().uri("/unused?days=-1").to_request()).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+#[ignore = "requires a migrated Postgres"]
+async fn test_usage_endpoints() {
+    let service = UsageService::new(db_pool().await, offline_redis_pool().await, false);
+    let code = format!("usage-{}", Uuid::new_v4());
+    create_template(&code).await;
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/templates/{template_code}/usage", web::get().to(get_template_usage))
+            .route("/unused", web::get().to(list_unused_templates)),
+    )
+    .await;
+
+    let req = TestRequest::get().uri(&format!("/templates/{}/usage", code)).to_request();
+    let body: serde_json::Value = call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"][0]["template_code"], code.as_str());
+    assert_eq!(body["data"][0]["render_count"], 0);
+
+    let req = TestRequest::get().uri("/templates/no-such-template/usage").to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    let req = TestRequest::get().uri("/unused?days=0").to_request();
+    let body: serde_json::Value = call_and_read_body_json(&app, req).await;
+    assert!(body["data"].as_array().unwrap().iter().any(|u| u["template_code"] == code.as_str()));
+}
\ No newline at end of file
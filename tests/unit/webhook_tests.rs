@@ -0,0 +1,352 @@
+//! The `#[ignore]`d cases need a migrated Postgres at `DATABASE_URL`:
+//! `cargo test -- --ignored`.
+
+use hmac::{Hmac, Mac};
+use serial_test::serial;
+use sha2::Sha256;
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::Duration;
+use templates_service::error::AppError;
+use templates_service::models::{
+    webhook_events, CreateWebhookRequest, Template, WebhookDelivery, WebhookEvent, WebhookSubscription,
+};
+use templates_service::services::webhook_service::{
+    backoff_secs, http_client, is_private_address, send_webhook, signature_header,
+};
+use templates_service::services::WebhookService;
+use uuid::Uuid;
+
+use super::support::{config, db_pool, offline_db_pool, spawn_http_stub, template, CapturedRequest};
+
+fn sample_template() -> Template {
+    Template {
+        version: 2,
+        ..template("welcome", "email_html", "Hi {{ name }}")
+    }
+}
+
+#[test]
+fn test_signature_header_matches_hmac_of_timestamp_and_body() {
+    let header = signature_header("whsec_test", 1700000000, r#"{"a":1}"#);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+    mac.update(br#"1700000000.{"a":1}"#);
+    let expected = hex::encode(mac.finalize().into_bytes());
+
+    assert_eq!(header, format!("t=1700000000,v1={}", expected));
+}
+
+#[test]
+fn test_signature_depends_on_secret_and_body() {
+    let base = signature_header("secret-a", 1, "body");
+    assert_ne!(base, signature_header("secret-b", 1, "body"));
+    assert_ne!(base, signature_header("secret-a", 1, "body2"));
+    assert_ne!(base, signature_header("secret-a", 2, "body"));
+}
+
+#[test]
+fn test_backoff_grows_exponentially_and_caps() {
+    assert_eq!(backoff_secs(1, 10, 3600), 10);
+    assert_eq!(backoff_secs(2, 10, 3600), 20);
+    assert_eq!(backoff_secs(4, 10, 3600), 80);
+    assert_eq!(backoff_secs(20, 10, 3600), 3600);
+    assert_eq!(backoff_secs(i32::MAX, 10, 3600), 3600);
+    assert_eq!(backoff_secs(0, 10, 3600), 10);
+}
+
+#[test]
+fn test_template_events_carry_identifiers() {
+    let created = WebhookEvent::template_created(&sample_template());
+    assert_eq!(created.event_type, webhook_events::TEMPLATE_CREATED);
+    assert_eq!(created.data["template_code"], "welcome");
+    assert_eq!(created.data["version"], 2);
+
+    let deleted = WebhookEvent::template_deleted("welcome", 2, &["en".to_string()]);
+    assert_eq!(deleted.event_type, webhook_events::TEMPLATE_DELETED);
+    assert_ne!(created.id, deleted.id);
+
+    let json = serde_json::to_value(&deleted).unwrap();
+    assert_eq!(json["type"], "template.deleted");
+    assert_eq!(json["id"], deleted.id.to_string());
+}
+
+#[actix_rt::test]
+async fn test_send_webhook_signs_request() {
+    let (base_url, requests) = spawn_http_stub("200 OK", "");
+    let url = format!("{}/hooks", base_url);
+    let client = reqwest::Client::new();
+    let event = WebhookEvent::template_deleted("welcome", 2, &["en".to_string()]);
+    let body = serde_json::to_string(&event).unwrap();
+    let delivery_id = Uuid::new_v4();
+
+    let attempt = send_webhook(&client, &url, "whsec_test", event.id, delivery_id, &event.event_type, &body).await;
+
+    assert!(attempt.succeeded());
+    assert_eq!(attempt.status_code, Some(200));
+
+    let request = requests.recv().unwrap();
+    assert_eq!(request.body_text(), body);
+    assert_eq!(request.header("x-webhook-id"), Some(event.id.to_string().as_str()));
+    assert_eq!(request.header("x-webhook-delivery"), Some(delivery_id.to_string().as_str()));
+    assert_eq!(request.header("x-webhook-event"), Some("template.deleted"));
+
+    let signature = request.header("x-webhook-signature").unwrap();
+    let timestamp: i64 = signature
+        .strip_prefix("t=")
+        .and_then(|s| s.split(',').next())
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(signature, signature_header("whsec_test", timestamp, &body));
+}
+
+#[actix_rt::test]
+async fn test_send_webhook_reports_non_success_status() {
+    let (base_url, _requests) = spawn_http_stub("500 Internal Server Error", "nope");
+    let url = format!("{}/hooks", base_url);
+    let client = reqwest::Client::new();
+
+    let attempt = send_webhook(&client, &url, "s", Uuid::new_v4(), Uuid::new_v4(), "template.created", "{}").await;
+
+    assert!(!attempt.succeeded());
+    assert_eq!(attempt.status_code, Some(500));
+    assert!(attempt.error.unwrap().contains("nope"));
+}
+
+#[actix_rt::test]
+async fn test_send_webhook_reports_connection_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+    drop(listener);
+    let client = reqwest::Client::new();
+
+    let attempt = send_webhook(&client, &url, "s", Uuid::new_v4(), Uuid::new_v4(), "template.created", "{}").await;
+
+    assert!(!attempt.succeeded());
+    assert_eq!(attempt.status_code, None);
+}
+
+#[test]
+fn test_private_and_link_local_addresses_are_recognised() {
+    for ip in [
+        "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+        "::1", "::", "fd00:ec2::254", "fe80::1", "::ffff:127.0.0.1",
+    ] {
+        assert!(is_private_address(ip.parse().unwrap()), "{} should be private", ip);
+    }
+    for ip in ["93.184.216.34", "8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+        assert!(!is_private_address(ip.parse().unwrap()), "{} should be public", ip);
+    }
+}
+
+fn webhook_request(url: &str) -> CreateWebhookRequest {
+    CreateWebhookRequest {
+        url: url.to_string(),
+        event_types: vec![webhook_events::TEMPLATE_CREATED.to_string()],
+        description: None,
+    }
+}
+
+#[actix_rt::test]
+async fn test_create_subscription_rejects_private_targets() {
+    let service = WebhookService::new(offline_db_pool(), &config(&[]));
+
+    for url in [
+        "http://169.254.169.254/latest/meta-data",
+        "http://127.0.0.1:8080/hooks",
+        "http://localhost/hooks",
+        "http://[::1]/hooks",
+        "https://10.0.0.5/hooks",
+        "http://[::ffff:192.168.0.1]/hooks",
+    ] {
+        let result = service.create_subscription(webhook_request(url)).await;
+        assert!(matches!(result, Err(AppError::InvalidContent(_))), "{} should be rejected", url);
+    }
+}
+
+#[actix_rt::test]
+async fn test_private_targets_allowed_when_configured() {
+    let service = WebhookService::new(
+        offline_db_pool(),
+        &config(&[("WEBHOOK_ALLOW_PRIVATE_TARGETS", "true")]),
+    );
+
+    let result = service.create_subscription(webhook_request("http://localhost/hooks")).await;
+
+    assert!(matches!(result, Err(AppError::DatabaseError(_))));
+}
+
+#[actix_rt::test]
+async fn test_delivery_refuses_host_names_resolving_to_private_addresses() {
+    let (base_url, _requests) = spawn_http_stub("200 OK", "");
+    let url = format!("{}/hooks", base_url.replace("127.0.0.1", "localhost"));
+
+    let guarded = http_client(5, false);
+    let attempt = send_webhook(&guarded, &url, "s", Uuid::new_v4(), Uuid::new_v4(), "template.created", "{}").await;
+    assert!(!attempt.succeeded());
+    assert_eq!(attempt.status_code, None);
+
+    let open = http_client(5, true);
+    let attempt = send_webhook(&open, &url, "s", Uuid::new_v4(), Uuid::new_v4(), "template.created", "{}").await;
+    assert!(attempt.succeeded());
+}
+
+/// Delivers to local stubs, gives up after two attempts and retries a
+/// minute after the first failure.
+async fn delivery_service() -> WebhookService {
+    WebhookService::new(
+        db_pool().await,
+        &config(&[
+            ("WEBHOOK_ALLOW_PRIVATE_TARGETS", "true"),
+            ("WEBHOOK_MAX_ATTEMPTS", "2"),
+            ("WEBHOOK_BACKOFF_BASE_SECS", "60"),
+        ]),
+    )
+}
+
+async fn subscribe(service: &WebhookService, base_url: &str) -> WebhookSubscription {
+    service
+        .create_subscription(webhook_request(&format!("{}/hooks", base_url)))
+        .await
+        .unwrap()
+}
+
+async fn only_delivery(service: &WebhookService, subscription: &WebhookSubscription) -> WebhookDelivery {
+    let mut deliveries = service.deliveries(subscription.id).await.unwrap();
+    assert_eq!(deliveries.len(), 1);
+    deliveries.remove(0)
+}
+
+async fn make_due(delivery: &WebhookDelivery) {
+    sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = now() WHERE id = $1")
+        .bind(delivery.id)
+        .execute(&db_pool().await)
+        .await
+        .unwrap();
+}
+
+fn received(requests: &mpsc::Receiver<CapturedRequest>) -> CapturedRequest {
+    requests.recv_timeout(Duration::from_secs(5)).expect("the stub should have been called")
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres"]
+async fn test_failed_delivery_is_retried_then_marked_failed() {
+    let service = delivery_service().await;
+    let (base_url, requests) = spawn_http_stub("500 Internal Server Error", r#"{"error":"down"}"#);
+    let subscription = subscribe(&service, &base_url).await;
+    let event = WebhookEvent::template_created(&sample_template());
+
+    assert_eq!(service.enqueue(&event).await.unwrap(), 1);
+    assert_eq!(service.enqueue(&event).await.unwrap(), 0);
+
+    assert!(service.dispatch_due().await.unwrap() >= 1);
+    let request = received(&requests);
+    assert_eq!(request.header("x-webhook-id"), Some(event.id.to_string().as_str()));
+
+    let delivery = only_delivery(&service, &subscription).await;
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_status_code, Some(500));
+    assert!(delivery.last_error.as_deref().unwrap().contains("down"));
+    assert!(delivery.next_attempt_at > chrono::Utc::now() + chrono::Duration::seconds(30));
+    assert!(delivery.delivered_at.is_none());
+
+    // Not due yet, so a second pass leaves it alone.
+    service.dispatch_due().await.unwrap();
+    assert!(requests.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(only_delivery(&service, &subscription).await.attempts, 1);
+
+    make_due(&delivery).await;
+    service.dispatch_due().await.unwrap();
+    received(&requests);
+
+    let delivery = only_delivery(&service, &subscription).await;
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.attempts, 2);
+
+    make_due(&delivery).await;
+    service.dispatch_due().await.unwrap();
+    assert!(requests.recv_timeout(Duration::from_millis(200)).is_err());
+
+    service.deactivate_subscription(subscription.id).await.unwrap();
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres"]
+async fn test_successful_delivery_is_logged_and_signed() {
+    let service = delivery_service().await;
+    let (base_url, requests) = spawn_http_stub("200 OK", "{}");
+    let subscription = subscribe(&service, &base_url).await;
+    let event = WebhookEvent::template_created(&sample_template());
+
+    service.enqueue(&event).await.unwrap();
+    service.dispatch_due().await.unwrap();
+
+    let request = received(&requests);
+    let delivery = only_delivery(&service, &subscription).await;
+    assert_eq!(delivery.status, "succeeded");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_status_code, Some(200));
+    assert!(delivery.last_error.is_none());
+    assert!(delivery.delivered_at.is_some());
+    assert_eq!(delivery.event_id, event.id);
+    assert_eq!(delivery.event_type, webhook_events::TEMPLATE_CREATED);
+
+    assert_eq!(request.header("x-webhook-delivery"), Some(delivery.id.to_string().as_str()));
+    let signature = request.header("x-webhook-signature").unwrap();
+    let timestamp: i64 = signature[2..signature.find(',').unwrap()].parse().unwrap();
+    assert_eq!(signature, signature_header(&subscription.secret, timestamp, &request.body_text()));
+
+    service.deactivate_subscription(subscription.id).await.unwrap();
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres"]
+async fn test_concurrent_dispatchers_claim_a_delivery_once() {
+    let service = delivery_service().await;
+    let (base_url, requests) = spawn_http_stub("200 OK", "{}");
+    let subscription = subscribe(&service, &base_url).await;
+
+    service
+        .enqueue(&WebhookEvent::template_created(&sample_template()))
+        .await
+        .unwrap();
+    let (first, second) = futures::join!(service.dispatch_due(), service.dispatch_due());
+    first.unwrap();
+    second.unwrap();
+
+    received(&requests);
+    assert!(requests.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(only_delivery(&service, &subscription).await.attempts, 1);
+
+    service.deactivate_subscription(subscription.id).await.unwrap();
+}
+
+#[actix_rt::test]
+#[serial]
+#[ignore = "requires a migrated Postgres"]
+async fn test_deactivation_cancels_pending_deliveries() {
+    let service = delivery_service().await;
+    let (base_url, requests) = spawn_http_stub("500 Internal Server Error", "{}");
+    let subscription = subscribe(&service, &base_url).await;
+
+    service
+        .enqueue(&WebhookEvent::template_created(&sample_template()))
+        .await
+        .unwrap();
+    service.dispatch_due().await.unwrap();
+    received(&requests);
+
+    service.deactivate_subscription(subscription.id).await.unwrap();
+    let delivery = only_delivery(&service, &subscription).await;
+    assert_eq!(delivery.status, "cancelled");
+
+    make_due(&delivery).await;
+    service.dispatch_due().await.unwrap();
+    assert!(requests.recv_timeout(Duration::from_millis(200)).is_err());
+}
\ No newline at end of file